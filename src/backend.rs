@@ -0,0 +1,500 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    mem,
+    ops::Range,
+};
+
+#[cfg(feature = "serial")]
+use crate::{style::strip_ansi, theme::theme};
+use crate::{
+    block::RenderMode,
+    terminal::{self, Stream},
+};
+
+/// Something that draws to a terminal.
+///
+/// Every escape sequence lytelog sends while drawing tasks goes through
+/// a backend, one primitive at a time. The default backend writes ANSI
+/// escape sequences. A backend of your own, handed to
+/// [`Logger::with_backend`](crate::Logger::with_backend), can draw
+/// some other way or record what is drawn.
+///
+/// Calls that move the cursor by zero lines are never made.
+pub trait TerminalBackend: Send {
+    /// Writes text at the cursor. The text may contain newlines and
+    /// SGR escape sequences for colors, and nothing else.
+    fn write_styled(&mut self, text: &str);
+
+    /// Moves the cursor up a number of lines, staying in its column.
+    fn move_up(&mut self, lines: usize);
+
+    /// Moves the cursor down a number of lines, staying in its column.
+    fn move_down(&mut self, lines: usize);
+
+    /// Moves the cursor to a column of its line, counting from 0.
+    fn move_to(&mut self, column: usize);
+
+    /// Moves the cursor to the start of a line of the terminal, counting from 0 at the top.
+    fn move_to_line(&mut self, line: usize);
+
+    /// Saves where the cursor is, for `restore_cursor`.
+    fn save_cursor(&mut self);
+
+    /// Moves the cursor back to where it was saved.
+    fn restore_cursor(&mut self);
+
+    /// Erases from the cursor to the end of its line.
+    fn clear_line(&mut self);
+
+    /// Erases from the cursor to the end of the screen.
+    fn clear_below(&mut self);
+
+    /// Makes only the given lines of the terminal scroll, counting from 0 at
+    /// the top, or the whole terminal again for `None`. This is only used by
+    /// `RenderMode::Dashboard`, and may move the cursor anywhere.
+    fn set_scroll_region(&mut self, lines: Option<Range<usize>>);
+
+    fn hide_cursor(&mut self);
+
+    fn show_cursor(&mut self);
+
+    /// Returns the width of the terminal, or `None` if the backend does not
+    /// draw to a terminal, in which case rows are never cut to fit. A width
+    /// of 0, like that of a pty whose size was never set, counts as unknown.
+    fn width(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the height of the terminal, if it is known.
+    ///
+    /// Rows that scroll off the top of the terminal can not be redrawn, so while
+    /// the running tasks take up more lines than this, only the last of them
+    /// are drawn, and the whole tree is printed once the outermost task ends.
+    fn height(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns how running tasks have to be drawn on the backend, which
+    /// takes the place of `set_render_mode`, or `None` for any way.
+    ///
+    /// A backend that can not move the cursor returns `RenderMode::Static`
+    /// or `RenderMode::Plain`, which never call the methods that move it.
+    fn render_mode(&self) -> Option<RenderMode> {
+        None
+    }
+
+    /// Returns false if tasks should only be printed once they end in the
+    /// static and plain render modes, rather than also when they start.
+    fn prints_started_tasks(&self) -> bool {
+        true
+    }
+
+    /// Sends out everything drawn since the last flush.
+    ///
+    /// The renderer flushes once for each batch of changes, so a backend
+    /// may buffer everything until then.
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Writes ANSI escape sequences, which is how lytelog draws by default.
+pub(crate) struct AnsiBackend {
+    writer: Box<dyn Write + Send>,
+    /// the stream of the writer, if it may be a terminal
+    stream: Option<Stream>,
+    /// what has been drawn since the last flush
+    buffer: String,
+}
+
+impl AnsiBackend {
+    pub fn new(writer: Box<dyn Write + Send>, stream: Option<Stream>) -> Self {
+        Self { writer, stream, buffer: String::new() }
+    }
+}
+
+impl TerminalBackend for AnsiBackend {
+    fn write_styled(&mut self, text: &str) {
+        self.buffer += text;
+    }
+
+    fn move_up(&mut self, lines: usize) {
+        // `\x1b[0A` still moves up a line
+        if lines > 0 {
+            _ = write!(self.buffer, "\x1b[{lines}A");
+        }
+    }
+
+    fn move_down(&mut self, lines: usize) {
+        if lines > 0 {
+            _ = write!(self.buffer, "\x1b[{lines}B");
+        }
+    }
+
+    fn move_to(&mut self, column: usize) {
+        match column {
+            0 => self.buffer += "\r",
+            column => _ = write!(self.buffer, "\x1b[{}G", column + 1),
+        }
+    }
+
+    fn move_to_line(&mut self, line: usize) {
+        _ = write!(self.buffer, "\x1b[{}H", line + 1);
+    }
+
+    fn save_cursor(&mut self) {
+        self.buffer += "\x1b[s";
+    }
+
+    fn restore_cursor(&mut self) {
+        self.buffer += "\x1b[u";
+    }
+
+    fn clear_line(&mut self) {
+        self.buffer += "\x1b[K";
+    }
+
+    fn clear_below(&mut self) {
+        self.buffer += "\x1b[J";
+    }
+
+    fn set_scroll_region(&mut self, lines: Option<Range<usize>>) {
+        match lines {
+            Some(lines) => _ = write!(self.buffer, "\x1b[{};{}r", lines.start + 1, lines.end),
+            None => self.buffer += "\x1b[r",
+        }
+    }
+
+    fn hide_cursor(&mut self) {
+        self.buffer += "\x1b[?25l";
+    }
+
+    fn show_cursor(&mut self) {
+        self.buffer += "\x1b[?25h";
+    }
+
+    fn width(&self) -> Option<usize> {
+        self.stream.and_then(terminal::size).map(|(width, _)| width)
+    }
+
+    fn height(&self) -> Option<usize> {
+        self.stream.and_then(terminal::size).map(|(_, height)| height)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        // most systems flush stdout by newlines. since rows
+        // are redrawn without printing newlines, the writer
+        // needs to be flushed explicitly.
+        self.writer.write_all(mem::take(&mut self.buffer).as_bytes())?;
+        self.writer.flush()
+    }
+}
+
+/// Draws with crossterm, which moves the cursor through the console
+/// API on versions of Windows that do not understand escape sequences.
+///
+/// ```no_run
+/// use lytelog::{CrosstermBackend, Logger};
+///
+/// let logger = Logger::with_backend(CrosstermBackend::new(std::io::stdout()));
+/// ```
+#[cfg(feature = "crossterm")]
+pub struct CrosstermBackend<W: Write + Send> {
+    writer: W,
+}
+
+#[cfg(feature = "crossterm")]
+impl<W: Write + Send> CrosstermBackend<W> {
+    /// Creates a backend that draws to a writer, which is
+    /// expected to be the terminal that crossterm measures.
+    pub fn new(writer: W) -> Self {
+        // turns on escape sequences in the Windows console, where it can
+        #[cfg(windows)]
+        crossterm::ansi_support::supports_ansi();
+
+        Self { writer }
+    }
+}
+
+#[cfg(feature = "crossterm")]
+impl<W: Write + Send> TerminalBackend for CrosstermBackend<W> {
+    fn write_styled(&mut self, text: &str) {
+        _ = crossterm::queue!(self.writer, crossterm::style::Print(text));
+    }
+
+    fn move_up(&mut self, lines: usize) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::MoveUp(saturate(lines)));
+    }
+
+    fn move_down(&mut self, lines: usize) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::MoveDown(saturate(lines)));
+    }
+
+    fn move_to(&mut self, column: usize) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::MoveToColumn(saturate(column)));
+    }
+
+    fn move_to_line(&mut self, line: usize) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::MoveTo(0, saturate(line)));
+    }
+
+    fn save_cursor(&mut self) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::SavePosition);
+    }
+
+    fn restore_cursor(&mut self) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::RestorePosition);
+    }
+
+    fn clear_line(&mut self) {
+        _ = crossterm::queue!(self.writer, crossterm::terminal::Clear(crossterm::terminal::ClearType::UntilNewLine));
+    }
+
+    fn clear_below(&mut self) {
+        _ = crossterm::queue!(self.writer, crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown));
+    }
+
+    fn set_scroll_region(&mut self, lines: Option<Range<usize>>) {
+        // crossterm has no command for this, so the escape sequence is written as is
+        let escape = match lines {
+            Some(lines) => format!("\x1b[{};{}r", lines.start + 1, lines.end),
+            None => "\x1b[r".to_string(),
+        };
+
+        _ = crossterm::queue!(self.writer, crossterm::style::Print(escape));
+    }
+
+    fn hide_cursor(&mut self) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::Hide);
+    }
+
+    fn show_cursor(&mut self) {
+        _ = crossterm::queue!(self.writer, crossterm::cursor::Show);
+    }
+
+    fn width(&self) -> Option<usize> {
+        crossterm::terminal::size().ok().map(|(width, _)| usize::from(width))
+    }
+
+    fn height(&self) -> Option<usize> {
+        crossterm::terminal::size().ok().map(|(_, height)| usize::from(height))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Returns a count of lines or columns as crossterm takes them.
+#[cfg(feature = "crossterm")]
+fn saturate(count: usize) -> u16 {
+    u16::try_from(count).unwrap_or(u16::MAX)
+}
+
+/// Prints compact lines of plain text to any writer, for devices whose only console is a serial port.
+///
+/// The cursor is never moved and no escape sequences are sent, only a line
+/// for each task as it starts and as it ends, and for each logged line.
+/// Each level of nesting is indented by a single space, every line can start
+/// with a prefix, and lines end with `\r\n`, as serial terminals expect:
+///
+/// ```no_run
+/// use lytelog::{Logger, SerialBackend};
+///
+/// # let uart = std::io::sink();
+/// let logger = Logger::with_backend(SerialBackend::new(uart).prefix("prov: ").hide_spinners());
+/// ```
+#[cfg(feature = "serial")]
+pub struct SerialBackend<W: Write + Send> {
+    writer: W,
+    prefix: String,
+    spinners: bool,
+    /// the complete lines drawn since the last flush
+    buffer: String,
+    /// the start of a line that has not ended yet
+    line: String,
+}
+
+#[cfg(feature = "serial")]
+impl<W: Write + Send> SerialBackend<W> {
+    /// Creates a backend that prints to a writer, like a UART.
+    pub fn new(writer: W) -> Self {
+        Self { writer, prefix: String::new(), spinners: true, buffer: String::new(), line: String::new() }
+    }
+
+    /// Starts every line with a prefix, like the name of the device.
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        Self { prefix: prefix.into(), ..self }
+    }
+
+    /// Only prints tasks once they end, instead of also printing a line with
+    /// a spinner standing still when they start, which halves what is sent.
+    pub fn hide_spinners(self) -> Self {
+        Self { spinners: false, ..self }
+    }
+
+    /// Adds a line that has ended to the buffer, with its indentation compacted.
+    fn push_line(&mut self, line: &str) {
+        let line = strip_ansi(line);
+        let text = line.trim_start_matches(' ');
+        let depth = (line.len() - text.len()) / theme().indent.max(1);
+
+        self.buffer += &self.prefix;
+        self.buffer += &" ".repeat(depth);
+        self.buffer += text;
+        self.buffer += "\r\n";
+    }
+}
+
+#[cfg(feature = "serial")]
+impl<W: Write + Send> TerminalBackend for SerialBackend<W> {
+    fn write_styled(&mut self, text: &str) {
+        self.line += text;
+
+        while let Some(end) = self.line.find('\n') {
+            let line: String = self.line.drain(..=end).collect();
+            self.push_line(line.trim_end_matches(['\r', '\n']));
+        }
+    }
+
+    fn move_up(&mut self, _lines: usize) {}
+    fn move_down(&mut self, _lines: usize) {}
+    fn move_to(&mut self, _column: usize) {}
+    fn move_to_line(&mut self, _line: usize) {}
+    fn save_cursor(&mut self) {}
+    fn restore_cursor(&mut self) {}
+    fn clear_line(&mut self) {}
+    fn clear_below(&mut self) {}
+    fn set_scroll_region(&mut self, _lines: Option<Range<usize>>) {}
+    fn hide_cursor(&mut self) {}
+    fn show_cursor(&mut self) {}
+
+    fn render_mode(&self) -> Option<RenderMode> {
+        Some(RenderMode::Plain)
+    }
+
+    fn prints_started_tasks(&self) -> bool {
+        self.spinners
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        self.writer.write_all(mem::take(&mut self.buffer).as_bytes())?;
+        self.writer.flush()
+    }
+}
+
+/// Something drawn by the block, kept until it is written out.
+#[derive(Debug)]
+enum Command {
+    Write(String),
+    MoveUp(usize),
+    MoveDown(usize),
+    MoveTo(usize),
+    MoveToLine(usize),
+    SaveCursor,
+    RestoreCursor,
+    ClearLine,
+    ClearBelow,
+    SetScrollRegion(Option<Range<usize>>),
+    HideCursor,
+    ShowCursor,
+}
+
+/// What has been drawn and not written out yet, to hand to a backend all at once.
+#[derive(Debug, Default)]
+pub(crate) struct Output(Vec<Command>);
+
+impl Output {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Draws everything kept so far on a backend, leaving nothing behind.
+    pub fn replay(&mut self, backend: &mut dyn TerminalBackend) {
+        for command in self.0.drain(..) {
+            match command {
+                Command::Write(text) => backend.write_styled(&text),
+                Command::MoveUp(lines) => backend.move_up(lines),
+                Command::MoveDown(lines) => backend.move_down(lines),
+                Command::MoveTo(column) => backend.move_to(column),
+                Command::MoveToLine(line) => backend.move_to_line(line),
+                Command::SaveCursor => backend.save_cursor(),
+                Command::RestoreCursor => backend.restore_cursor(),
+                Command::ClearLine => backend.clear_line(),
+                Command::ClearBelow => backend.clear_below(),
+                Command::SetScrollRegion(lines) => backend.set_scroll_region(lines),
+                Command::HideCursor => backend.hide_cursor(),
+                Command::ShowCursor => backend.show_cursor(),
+            }
+        }
+    }
+}
+
+impl TerminalBackend for Output {
+    fn write_styled(&mut self, text: &str) {
+        match self.0.last_mut() {
+            Some(Command::Write(last)) => *last += text,
+            _ if text.is_empty() => {}
+            _ => self.0.push(Command::Write(text.to_string())),
+        }
+    }
+
+    fn move_up(&mut self, lines: usize) {
+        if lines > 0 {
+            self.0.push(Command::MoveUp(lines));
+        }
+    }
+
+    fn move_down(&mut self, lines: usize) {
+        if lines > 0 {
+            self.0.push(Command::MoveDown(lines));
+        }
+    }
+
+    fn move_to(&mut self, column: usize) {
+        self.0.push(Command::MoveTo(column));
+    }
+
+    fn move_to_line(&mut self, line: usize) {
+        self.0.push(Command::MoveToLine(line));
+    }
+
+    fn save_cursor(&mut self) {
+        self.0.push(Command::SaveCursor);
+    }
+
+    fn restore_cursor(&mut self) {
+        self.0.push(Command::RestoreCursor);
+    }
+
+    fn clear_line(&mut self) {
+        self.0.push(Command::ClearLine);
+    }
+
+    fn clear_below(&mut self) {
+        self.0.push(Command::ClearBelow);
+    }
+
+    fn set_scroll_region(&mut self, lines: Option<Range<usize>>) {
+        self.0.push(Command::SetScrollRegion(lines));
+    }
+
+    fn hide_cursor(&mut self) {
+        self.0.push(Command::HideCursor);
+    }
+
+    fn show_cursor(&mut self) {
+        self.0.push(Command::ShowCursor);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,1162 @@
+use std::{
+    fmt::Write,
+    ops::Range,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    backend::{Output, TerminalBackend},
+    environment,
+    format,
+    style::{paint, strip_ansi, style, truncate, visible_width},
+    task::Task,
+    terminal::{self, Stream},
+    theme::theme,
+};
+
+/// What happens to running tasks that are too long to fit on one line.
+///
+/// Finished tasks and logged lines are never cut short, they
+/// wrap onto as many lines as they need.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Cut the line at the edge of the terminal and end it with `…`.
+    #[default]
+    Truncate,
+    /// Let the line wrap onto the lines below.
+    Wrap,
+}
+
+static WRAP: AtomicBool = AtomicBool::new(false);
+
+/// Sets what happens to running tasks that are wider than the terminal.
+pub fn set_overflow(overflow: Overflow) {
+    WRAP.store(overflow == Overflow::Wrap, Ordering::Relaxed);
+}
+
+/// How the block of running tasks is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Degrade as far as the output needs: `Plain` where it is redirected to
+    /// a file or a pipe, `Static` where `TERM` is `dumb` or its terminfo entry
+    /// can not move the cursor up and erase lines, `Relative` where `TERM`
+    /// suggests a terminal that may not save and restore the cursor properly,
+    /// like GNU screen or a serial console, and `Tree` everywhere else.
+    #[default]
+    Auto,
+    /// Draw the tree of tasks and redraw its rows in place as they change.
+    Tree,
+    /// Draw the tree like `Tree`, except that the cursor is only ever moved
+    /// relative to where it is, and never saved and restored. This costs a
+    /// few more bytes per redraw.
+    Relative,
+    /// Never move the cursor up. Finished tasks and logged lines are printed
+    /// on lines of their own as they happen, indented by depth, and running tasks
+    /// are shown on a single status line beneath them that is erased and printed
+    /// again. This keeps the scrollback intact in terminals and multiplexers that
+    /// mangle cursor movement.
+    AppendOnly,
+    /// Print finished tasks and logged lines like `AppendOnly`, and draw the
+    /// running tasks in a region at the bottom of the terminal that stays put
+    /// while everything above it scrolls, like the output of docker build.
+    /// Lines printed to stdout while tasks run scroll above the region too.
+    ///
+    /// The region is set aside with a scroll region, which is given back once
+    /// the outermost task ends. Without a terminal whose height is known,
+    /// running tasks are shown on a status line like `AppendOnly`.
+    Dashboard,
+    /// Never move the cursor at all, and draw nothing that changes. A line is
+    /// printed when a task starts, with its spinner standing still, and another
+    /// when it ends, along with logged lines, each indented by depth.
+    Static,
+    /// Print the same lines as `Static`, as plain text without any escape
+    /// sequences, for logs and CI systems that keep output as it is.
+    Plain,
+}
+
+static RENDER_MODE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets how running tasks are drawn, from the next time a task starts while none are running.
+pub fn set_render_mode(mode: RenderMode) {
+    let value = match mode {
+        RenderMode::Auto => 0,
+        RenderMode::Tree => 1,
+        RenderMode::Relative => 2,
+        RenderMode::AppendOnly => 3,
+        RenderMode::Dashboard => 4,
+        RenderMode::Static => 5,
+        RenderMode::Plain => 6,
+    };
+
+    RENDER_MODE.store(value, Ordering::Relaxed);
+}
+
+/// Returns how running tasks are drawn to a stream, which is never `Auto`.
+pub(crate) fn render_mode(stream: Option<Stream>) -> RenderMode {
+    match RENDER_MODE.load(Ordering::Relaxed) {
+        1 => RenderMode::Tree,
+        2 => RenderMode::Relative,
+        3 => RenderMode::AppendOnly,
+        4 => RenderMode::Dashboard,
+        5 => RenderMode::Static,
+        6 => RenderMode::Plain,
+        _ => {
+            // escape sequences only end up as noise in a file or a pipe
+            if stream.is_some_and(|stream| !terminal::is_terminal(stream)) {
+                return RenderMode::Plain;
+            }
+
+            if environment::is_dumb_terminal() || !terminal::moves_cursor() {
+                return RenderMode::Static;
+            }
+
+            // screen, and the VT100s that serial consoles emulate, do not
+            // all understand `\x1b[s` and `\x1b[u` the way other terminals do
+            let term = environment::var("TERM").unwrap_or_default();
+
+            if term.starts_with("screen") || term.starts_with("vt") {
+                RenderMode::Relative
+            } else {
+                RenderMode::Tree
+            }
+        }
+    }
+}
+
+/// A row of the block of output drawn for the outermost running task.
+#[derive(Clone, Debug)]
+pub(crate) struct Row {
+    /// how deeply the row is nested under the outermost task
+    pub depth: usize,
+    pub kind: RowKind,
+    /// the text of the row when it was last drawn
+    drawn: String,
+    /// how many lines of the terminal the row took up when it was last drawn,
+    /// or 0 if it is not drawn because only the rows after it fit on the terminal
+    lines: usize,
+    /// set once the row has been printed for good in append-only mode
+    printed: bool,
+    /// set once the start of the task on the row has been printed in static and plain modes
+    started: bool,
+}
+
+impl Row {
+    pub fn new(depth: usize, kind: RowKind) -> Self {
+        Self { depth, kind, drawn: String::new(), lines: 0, printed: false, started: false }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum RowKind {
+    /// a task that is still running, by id
+    Running(u64),
+    /// a task that has ended, with its symbol and final message
+    Finished(String),
+    /// a line printed with `log!`
+    Text(String),
+    /// a line of the live tail of a running task, like the output
+    /// of a child process, by the id of the task and the line
+    Tail(u64, usize),
+}
+
+impl RowKind {
+    /// Returns true for rows printed beneath a task rather than being tasks themselves.
+    fn is_text(&self) -> bool {
+        matches!(self, RowKind::Text(_) | RowKind::Tail(..))
+    }
+}
+
+/// Every row drawn since the outermost running task started.
+///
+/// Rows are kept so that they can be redrawn with the right tree
+/// connectors as tasks start and end, or after the terminal has been
+/// used by something else. The cursor always sits at the end of the
+/// last row, and rows may take up more than one line of the terminal.
+///
+/// Rows that have scrolled off the top of the terminal can not be
+/// redrawn, so while the rows would not fit, only the last rows that do
+/// are drawn. Once the block is closed, the whole tree is printed.
+#[derive(Debug, Default)]
+pub(crate) struct Block {
+    pub rows: Vec<Row>,
+    /// the width of the terminal, if drawing to one
+    pub width: Option<usize>,
+    /// the height of the terminal, if it is known
+    pub height: Option<usize>,
+    /// what has been drawn and not written out yet
+    pub out: Output,
+    pub mode: RenderMode,
+    /// set if tasks are only printed once they end in static and plain modes
+    pub skip_start_lines: bool,
+    /// the status line beneath the printed rows in append-only mode, as it was last drawn
+    status: String,
+    /// the lines of the region at the bottom of the terminal in dashboard mode, as they were last drawn
+    dashboard: Vec<String>,
+    /// the first line of the terminal that belongs to the region of the dashboard
+    dashboard_top: usize,
+}
+
+impl Block {
+    /// Returns the index of the row of a running task.
+    pub fn position(&self, id: u64) -> Option<usize> {
+        self.rows.iter().rposition(|row| matches!(row.kind, RowKind::Running(row_id) if row_id == id))
+    }
+
+    /// Returns the indices of the rows of the live tail of a task.
+    pub fn tail_of(&self, id: u64) -> Range<usize> {
+        let is_tail = |row: &Row| matches!(row.kind, RowKind::Tail(row_id, _) if row_id == id);
+
+        match self.rows.iter().position(is_tail) {
+            Some(start) => start..start + self.rows[start..].iter().take_while(|row| is_tail(row)).count(),
+            None => 0..0,
+        }
+    }
+
+    /// Returns where a row belongs after the rows of a running task and
+    /// everything nested under it, or at the end if it has no row.
+    pub fn end_of(&self, id: Option<u64>) -> usize {
+        let Some(index) = id.and_then(|id| self.position(id)) else {
+            return self.rows.len();
+        };
+
+        let depth = self.rows[index].depth;
+
+        self.rows[index + 1..]
+            .iter()
+            .position(|row| row.depth <= depth)
+            .map_or(self.rows.len(), |offset| index + 1 + offset)
+    }
+
+    /// Inserts a row, redrawing the rows it moves and the rows whose connectors it changes.
+    pub fn insert(&mut self, index: usize, mut row: Row, tasks: &[Task]) {
+        if self.appends() {
+            self.rows.insert(index, row);
+            return self.append(tasks);
+        }
+
+        // rows before those that fit on the terminal change nothing that is drawn
+        if index < self.first_drawn() {
+            self.rows.insert(index, row);
+            return self.fit_height(tasks);
+        }
+
+        let depth = row.depth;
+
+        if index == self.rows.len() {
+            if !self.rows.is_empty() {
+                self.out.write_styled("\n");
+            }
+
+            // the new row starts out on the empty line under the cursor
+            row.lines = 1;
+            self.rows.push(row);
+            self.draw(index, tasks);
+        } else {
+            // the rows after the new one all move down a line, so they
+            // are erased and drawn again with the new row above them.
+            let below: usize = self.rows[index..].iter().map(|row| row.lines).sum();
+            self.clear_lines(below);
+
+            self.rows.insert(index, row);
+
+            for next in index..self.rows.len() {
+                if next > index {
+                    self.out.write_styled("\n");
+                }
+
+                let line = self.fit(next, tasks);
+                self.print(next, line);
+            }
+        }
+
+        // only the rows since the parent of the new row can have
+        // connectors that lead down to it.
+        let parent = self.rows[..index].iter().rposition(|row| row.depth < depth).unwrap_or(0);
+
+        for index in parent + 1..index {
+            self.draw(index, tasks);
+        }
+
+        self.fit_height(tasks);
+    }
+
+    /// Removes rows, redrawing the rows they leave behind.
+    pub fn remove(&mut self, range: Range<usize>, tasks: &[Task]) {
+        if range.is_empty() {
+            return;
+        }
+
+        if self.appends() {
+            self.rows.drain(range);
+            return self.append(tasks);
+        }
+
+        let first = self.first_drawn();
+
+        if range.start < first {
+            let drawn = self.drawn_lines();
+            let visible = range.end > first;
+
+            self.rows.drain(range);
+
+            if visible {
+                self.clear_lines(drawn);
+                self.draw_all(tasks);
+            }
+
+            return self.fit_height(tasks);
+        }
+
+        let start = range.start;
+        let below: usize = self.rows[start..].iter().map(|row| row.lines).sum();
+        self.clear_lines(below);
+
+        self.rows.drain(range);
+
+        if start == self.rows.len() {
+            // the cursor is left on the line after the new last row,
+            // so that row is drawn again to end up at its end.
+            if let Some(last) = self.rows.last() {
+                self.out.move_up(last.lines);
+
+                let line = self.fit(start - 1, tasks);
+                self.print(start - 1, line);
+            }
+        } else {
+            for next in start..self.rows.len() {
+                if next > start {
+                    self.out.write_styled("\n");
+                }
+
+                let line = self.fit(next, tasks);
+                self.print(next, line);
+            }
+        }
+
+        // connectors above that led down to the removed rows may end sooner
+        let depth = self.rows.get(start).map_or(0, |row| row.depth);
+        let parent = self.rows[..start].iter().rposition(|row| row.depth < depth).unwrap_or(0);
+
+        for index in parent + 1..start {
+            self.draw(index, tasks);
+        }
+
+        self.fit_height(tasks);
+    }
+
+    /// Redraws a single row in place.
+    pub fn draw(&mut self, index: usize, tasks: &[Task]) {
+        if self.appends() {
+            return self.append(tasks);
+        }
+
+        // the row does not fit on the terminal
+        if self.rows[index].lines == 0 {
+            return;
+        }
+
+        let line = self.fit(index, tasks);
+        let lines = self.lines(&line);
+        let below: usize = self.rows[index + 1..].iter().map(|row| row.lines).sum();
+        let up = below + self.rows[index].lines - 1;
+
+        // rewrite the row:
+        // save_cursor      : save the cursor's current position
+        // move_up(n)       : move the cursor up to the first line of the row
+        // {changes}        : rewrite the part of the row that changed
+        // restore_cursor   : restore the saved cursor position
+        //
+        // in relative mode, the cursor is moved back down to the end of
+        // the last row instead of being restored, since the rows below
+        // and where the last one ends are known.
+        //
+        // the last row is rewritten up to its end, which is already
+        // where the cursor belongs, so nothing is saved or restored.
+        //
+        // if the row now takes up a different number of lines, every
+        // row after it moves, so they are all erased and drawn again.
+
+        if lines == self.rows[index].lines && line == self.rows[index].drawn {
+            return;
+        }
+
+        if lines != self.rows[index].lines {
+            self.clear_lines(up + 1);
+            self.print(index, line);
+
+            for index in index + 1..self.rows.len() {
+                self.out.write_styled("\n");
+
+                let line = self.fit(index, tasks);
+                self.print(index, line);
+            }
+
+            self.fit_height(tasks);
+        } else if below == 0 {
+            self.out.move_up(up);
+            self.print_changes(index, line);
+        } else if self.mode == RenderMode::Relative {
+            self.out.move_up(up);
+            self.print_changes(index, line);
+            self.return_to_end(below);
+        } else {
+            self.out.save_cursor();
+            self.out.move_up(up);
+            self.print_changes(index, line);
+            self.out.restore_cursor();
+        }
+    }
+
+    /// Redraws every row that fits on the terminal, starting from the cursor's row.
+    pub fn draw_all(&mut self, tasks: &[Task]) {
+        if self.appends() {
+            return self.append(tasks);
+        }
+
+        let first = self.first_fitting(tasks);
+
+        for row in &mut self.rows[..first] {
+            row.lines = 0;
+            row.drawn.clear();
+        }
+
+        for index in first..self.rows.len() {
+            if index > first {
+                self.out.write_styled("\n");
+            }
+
+            let line = self.fit(index, tasks);
+            self.print(index, line);
+        }
+    }
+
+    /// Erases the block, leaving the cursor where the block started.
+    pub fn clear(&mut self) {
+        if !self.dashboard.is_empty() {
+            self.dashboard.clear();
+            return self.reserve(0);
+        }
+
+        if self.appends() {
+            // nothing needs erasing without a status line
+            if !self.status.is_empty() {
+                self.out.move_to(0);
+                self.out.clear_line();
+                self.status.clear();
+            }
+
+            return;
+        }
+
+        self.clear_lines(self.drawn_lines());
+    }
+
+    /// Leaves the block behind once its outermost task has ended, with the cursor on the line after it.
+    pub fn close(&mut self, tasks: &[Task]) {
+        match self.mode {
+            // everything was printed already, except for the status line
+            _ if self.appends() => self.clear(),
+            // the rows that did not fit on the terminal are printed with the rest of the tree
+            _ if self.first_drawn() > 0 => {
+                self.clear();
+
+                for index in 0..self.rows.len() {
+                    let line = self.fit(index, tasks);
+                    self.out.write_styled(&(line + "\n"));
+                }
+            }
+            _ => self.out.write_styled("\n"),
+        }
+
+        self.rows.clear();
+    }
+
+    /// Returns the text of the rows that have not been printed for good, to print once the block is gone.
+    pub fn remaining(&self, tasks: &[Task]) -> Vec<String> {
+        match self.mode {
+            RenderMode::Plain => {
+                (0..self.rows.len()).filter_map(|index| self.permanent(index, tasks)).map(|line| strip_ansi(&line)).collect()
+            }
+            _ if self.appends() => (0..self.rows.len()).filter_map(|index| self.permanent(index, tasks)).collect(),
+            _ => (0..self.rows.len()).map(|index| self.render(index, tasks)).collect(),
+        }
+    }
+
+    /// Redraws the block from scratch after the terminal changed width.
+    ///
+    /// Terminals rewrap lines when they are resized, so rows that no
+    /// longer fit take up more than one line and the block now starts
+    /// further up than it did.
+    pub fn redraw_resized(&mut self, width: usize, tasks: &[Task]) {
+        self.width = Some(width);
+
+        if self.appends() {
+            self.clear();
+            return self.append(tasks);
+        }
+
+        let lines = self
+            .rows
+            .iter()
+            .filter(|row| row.lines > 0)
+            .map(|row| visible_width(&row.drawn).div_ceil(width.max(1)).max(1))
+            .sum();
+
+        self.clear_lines(lines);
+        self.draw_all(tasks);
+    }
+
+    /// Returns true if finished rows are printed for good as they finish, rather than redrawn in place.
+    fn appends(&self) -> bool {
+        matches!(self.mode, RenderMode::AppendOnly | RenderMode::Dashboard | RenderMode::Static | RenderMode::Plain)
+    }
+
+    /// Returns true if the block never moves the cursor, or draws anything that changes.
+    pub fn is_static(&self) -> bool {
+        matches!(self.mode, RenderMode::Static | RenderMode::Plain)
+    }
+
+    /// Returns the index of the first row that is drawn.
+    fn first_drawn(&self) -> usize {
+        self.rows.iter().position(|row| row.lines > 0).unwrap_or(self.rows.len())
+    }
+
+    /// Returns how many lines of the terminal the drawn rows take up.
+    fn drawn_lines(&self) -> usize {
+        self.rows.iter().map(|row| row.lines).sum()
+    }
+
+    /// Returns the index of the first of the last rows that fit on the terminal.
+    ///
+    /// A line is left free, so that a new row can be printed beneath them
+    /// before they are drawn again. The last row is always drawn, even if
+    /// it does not fit on its own.
+    fn first_fitting(&self, tasks: &[Task]) -> usize {
+        let Some(height) = self.height else { return 0 };
+        let mut lines = 0;
+
+        for index in (0..self.rows.len()).rev() {
+            lines += self.lines(&self.fit(index, tasks));
+
+            if lines >= height && index + 1 < self.rows.len() {
+                return index + 1;
+            }
+        }
+
+        0
+    }
+
+    /// Draws only the last rows that fit once the drawn rows no longer fit on
+    /// the terminal, or rows that did not fit again once there is room for them.
+    fn fit_height(&mut self, tasks: &[Task]) {
+        let Some(height) = self.height else { return };
+        let drawn = self.drawn_lines();
+        let first = self.first_drawn();
+
+        if drawn >= height || (first > 0 && self.first_fitting(tasks) < first) {
+            self.clear_lines(drawn);
+            self.draw_all(tasks);
+        }
+    }
+
+    /// Moves the cursor from the last line of a row back to the end of the last
+    /// row, which takes up `below` lines beneath it along with the rows between.
+    fn return_to_end(&mut self, below: usize) {
+        let Some(last) = self.rows.last() else { return };
+        let columns = visible_width(&last.drawn);
+
+        // on the last line of a row that wraps, only what is left after the full lines is there
+        let column = match self.width {
+            Some(width) if columns > 0 => (columns - 1) % width + 1,
+            _ => columns,
+        };
+
+        self.out.move_down(below);
+        self.out.move_to(column);
+    }
+
+    /// Erases the last `lines` lines, ending on the first of them.
+    fn clear_lines(&mut self, lines: usize) {
+        if lines > 0 {
+            self.out.move_up(lines - 1);
+            self.out.move_to(0);
+            self.out.clear_below();
+        }
+    }
+
+    /// Prints a row from the start of the cursor's line, remembering how much of the terminal it took up.
+    fn print(&mut self, index: usize, line: String) {
+        self.out.move_to(0);
+        self.out.write_styled(&line);
+        self.out.clear_line();
+
+        self.rows[index].lines = self.lines(&line);
+        self.rows[index].drawn = line;
+    }
+
+    /// Prints only the part of a row that is different from when it was
+    /// last drawn, from the start of the cursor's line.
+    ///
+    /// Most redraws only turn a spinner or tick a clock, so this saves
+    /// rewriting the whole row over slow connections and keeps the
+    /// rest of it from flickering.
+    fn print_changes(&mut self, index: usize, line: String) {
+        let row = &self.rows[index];
+
+        // moving to a column only works within a single line
+        if row.lines > 1 {
+            return self.print(index, line);
+        }
+
+        let old = cells(&row.drawn);
+        let new = cells(&line);
+
+        let same = old.iter().zip(&new).take_while(|(old, new)| old == new).count();
+        let column: usize = new[..same].iter().map(|cell| cell.width).sum();
+
+        self.out.move_to(column);
+
+        // the colors in effect where the change starts
+        let mut changes = String::from("\x1b[0m");
+        changes.extend(new[..same].iter().map(|cell| cell.escapes));
+
+        let (old, new) = (&old[same..], &new[same..]);
+        let tail = old.iter().rev().zip(new.iter().rev()).take_while(|(old, new)| old == new).count();
+        let width = |cells: &[Cell]| cells.iter().map(|cell| cell.width).sum::<usize>();
+
+        // if the end of the row is the same and still in the same
+        // place, only what is between needs to be printed.
+        if tail > 0 && width(&old[..old.len() - tail]) == width(&new[..new.len() - tail]) {
+            changes.extend(new[..new.len() - tail].iter().map(|cell| cell.text));
+            changes += "\x1b[0m";
+
+            self.out.write_styled(&changes);
+
+            // the cursor is left at the end of the row
+            self.out.move_to(visible_width(&line));
+        } else {
+            changes.extend(new.iter().map(|cell| cell.text));
+
+            self.out.write_styled(&changes);
+            self.out.clear_line();
+        }
+
+        self.rows[index].drawn = line;
+    }
+
+    /// Returns how many lines of the terminal a line of text takes up.
+    fn lines(&self, line: &str) -> usize {
+        match self.width {
+            Some(width) => visible_width(line).div_ceil(width.max(1)).max(1),
+            None => 1,
+        }
+    }
+
+    /// Returns the text of a row, cut to fit the terminal if it is a running task.
+    fn fit(&self, index: usize, tasks: &[Task]) -> String {
+        let (line, status) = self.render_parts(index, tasks);
+        let wrap = WRAP.load(Ordering::Relaxed);
+
+        // the last column is left empty, since some terminals
+        // wrap as soon as it is written to.
+        match (status, self.width) {
+            (Some(status), Some(width)) => {
+                let width = width.saturating_sub(1);
+                let fits = visible_width(&line) + 1 + visible_width(&status) <= width;
+
+                if wrap && !fits {
+                    return line + " " + &status;
+                }
+
+                // the status stays at the right edge, and the line is cut short before it
+                let line = truncate(&line, width.saturating_sub(visible_width(&status) + 1));
+                let padding = width.saturating_sub(visible_width(&line) + visible_width(&status));
+
+                line + &" ".repeat(padding) + &status
+            }
+            (Some(status), None) => line + " " + &status,
+            (None, Some(width)) if !wrap && matches!(self.rows[index].kind, RowKind::Running(_) | RowKind::Tail(..)) => {
+                truncate(&line, width.saturating_sub(1))
+            }
+            (None, _) => line,
+        }
+    }
+
+    /// Returns the full text of a row, including its tree connectors.
+    pub fn render(&self, index: usize, tasks: &[Task]) -> String {
+        match self.render_parts(index, tasks) {
+            (line, Some(status)) => line + " " + &status,
+            (line, None) => line,
+        }
+    }
+
+    /// Returns the text of a row and what goes in its status column, if it has one.
+    fn render_parts(&self, index: usize, tasks: &[Task]) -> (String, Option<String>) {
+        let (content, status) = self.content(index, tasks);
+        (self.prefix(index) + &content, status)
+    }
+
+    /// Like `render_parts`, without the tree connectors.
+    fn content(&self, index: usize, tasks: &[Task]) -> (String, Option<String>) {
+        let row = &self.rows[index];
+        let mut line = String::new();
+        let mut status = None;
+
+        match &row.kind {
+            RowKind::Running(id) => {
+                if let Some(task) = tasks.iter().find(|task| task.id == *id) {
+                    let theme = theme();
+                    let frame = task.spinner.frame(task.frame);
+
+                    let stalled = task.stalled_among(tasks);
+                    let color = if stalled.is_some() { theme.fail.color } else { task.tint().unwrap_or(theme.spinner_color) };
+
+                    // frames are padded to the widest one so the
+                    // message does not shift as the spinner turns.
+                    line += &paint(color, frame);
+                    line += &" ".repeat(task.spinner.width() - visible_width(frame) + 1);
+                    line += &task.body(tasks);
+
+                    if let Some(idle) = stalled {
+                        _ = write!(line, " (stalled {})", format::seconds(idle));
+                    }
+
+                    status = task.status();
+                }
+            }
+            RowKind::Finished(text) | RowKind::Text(text) => line += text,
+            RowKind::Tail(id, index) => {
+                if let Some(text) = tasks.iter().find(|task| task.id == *id).and_then(|task| task.tail.get(*index)) {
+                    _ = write!(line, "{}", style(text).dim());
+                }
+            }
+        }
+
+        (line, status)
+    }
+
+    /// Prints the rows that are done changing for good, then the status line beneath them.
+    ///
+    /// This is all that append-only mode draws, and it never moves the cursor
+    /// off the status line, so the rows are printed in the order they finish.
+    fn append(&mut self, tasks: &[Task]) {
+        let mut printed = String::new();
+
+        for index in 0..self.rows.len() {
+            if let Some(line) = self.start_line(index, tasks) {
+                printed += &line;
+                printed += "\n";
+                self.rows[index].started = true;
+            }
+
+            if let Some(line) = self.permanent(index, tasks).filter(|_| !self.rows[index].printed) {
+                printed += &line;
+                printed += "\n";
+                self.rows[index].printed = true;
+            }
+        }
+
+        if self.is_static() {
+            let printed = if self.mode == RenderMode::Plain { strip_ansi(&printed) } else { printed };
+            return self.out.write_styled(&printed);
+        }
+
+        // a region only fits on a terminal with room for it and a few lines above it
+        if let Some(height) = self.height.filter(|height| *height >= 4 && self.mode == RenderMode::Dashboard) {
+            return self.draw_dashboard(height, printed, tasks);
+        }
+
+        let status = self.status_line(tasks);
+
+        if printed.is_empty() && status == self.status {
+            return;
+        }
+
+        self.out.move_to(0);
+        self.out.clear_line();
+        self.out.write_styled(&(printed + &status));
+        self.out.clear_line();
+        self.status = status;
+    }
+
+    /// Prints lines above the region of the dashboard, then draws the lines of the region that changed.
+    ///
+    /// The cursor always stays at the start of the line after the last
+    /// line printed above the region, where anything else that is
+    /// printed ends up as well.
+    fn draw_dashboard(&mut self, height: usize, printed: String, tasks: &[Task]) {
+        let lines = self.dashboard_lines(height, tasks);
+        let top = height - lines.len();
+
+        // the region is set aside again when the terminal is resized as well
+        if lines.len() != self.dashboard.len() || top != self.dashboard_top {
+            self.reserve(lines.len());
+            self.dashboard = vec![String::new(); lines.len()];
+            self.dashboard_top = top;
+        }
+
+        self.out.write_styled(&printed);
+        let changed: Vec<usize> = (0..lines.len()).filter(|&index| lines[index] != self.dashboard[index]).collect();
+
+        if !changed.is_empty() {
+            self.out.save_cursor();
+
+            for index in changed {
+                self.out.move_to_line(top + index);
+                self.out.write_styled(&lines[index]);
+                self.out.clear_line();
+            }
+
+            self.out.restore_cursor();
+        }
+
+        self.dashboard = lines;
+    }
+
+    /// Sets aside the last `lines` lines of the terminal for the dashboard,
+    /// erasing what was set aside before, or gives them back for 0.
+    fn reserve(&mut self, lines: usize) {
+        let Some(height) = self.height else { return };
+
+        // setting the scroll region moves the cursor to the top of the terminal
+        self.out.save_cursor();
+        self.out.set_scroll_region(None);
+        self.out.restore_cursor();
+        self.out.clear_below();
+
+        if lines > 0 {
+            // what is above the cursor scrolls up if there is not enough room beneath it
+            self.out.write_styled(&"\n".repeat(lines));
+            self.out.move_up(lines);
+
+            self.out.save_cursor();
+            self.out.set_scroll_region(Some(0..height - lines));
+            self.out.restore_cursor();
+        }
+    }
+
+    /// Returns the lines of the dashboard, one for each running task or line of a
+    /// live tail, indented by depth. At most half of the terminal is used, and
+    /// tasks that do not fit are counted on the last line.
+    fn dashboard_lines(&self, height: usize, tasks: &[Task]) -> Vec<String> {
+        let indent = theme().indent;
+        let width = self.width.map_or(usize::MAX, |width| width.saturating_sub(1));
+
+        let mut lines: Vec<String> = (0..self.rows.len())
+            .filter(|&index| matches!(self.rows[index].kind, RowKind::Running(_) | RowKind::Tail(..)))
+            .map(|index| {
+                let line = match self.content(index, tasks) {
+                    (line, Some(status)) => line + " " + &status,
+                    (line, None) => line,
+                };
+
+                truncate(&(" ".repeat(indent * self.rows[index].depth) + &line), width)
+            })
+            .collect();
+
+        let room = height / 2;
+
+        if lines.len() > room {
+            let hidden = lines.len() - (room - 1);
+            lines.drain(..hidden);
+            lines.push(format!("(+{hidden} more)"));
+        }
+
+        lines
+    }
+
+    /// Returns the line printed when the task on a row starts in static and plain modes, if it has not been yet.
+    fn start_line(&self, index: usize, tasks: &[Task]) -> Option<String> {
+        let row = &self.rows[index];
+
+        match row.kind {
+            // a task that ended before it was drawn only has its end printed
+            RowKind::Running(id) if self.is_static() && !self.skip_start_lines && !row.started && tasks.iter().any(|task| task.id == id) => {
+                Some(" ".repeat(theme().indent * row.depth) + &self.content(index, tasks).0)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the line a row is printed as for good in append-only
+    /// mode, or `None` if the row is still changing.
+    fn permanent(&self, index: usize, tasks: &[Task]) -> Option<String> {
+        let row = &self.rows[index];
+
+        match row.kind {
+            RowKind::Finished(_) | RowKind::Text(_) => {
+                Some(" ".repeat(theme().indent * row.depth) + &self.content(index, tasks).0)
+            }
+            RowKind::Running(_) | RowKind::Tail(..) => None,
+        }
+    }
+
+    /// Returns the status line of append-only mode, which shows the last
+    /// running task and how many others are running.
+    fn status_line(&self, tasks: &[Task]) -> String {
+        let running: Vec<usize> = (0..self.rows.len())
+            .filter(|&index| matches!(self.rows[index].kind, RowKind::Running(_)))
+            .collect();
+
+        let Some(&last) = running.last() else { return String::new() };
+        let mut line = match self.content(last, tasks) {
+            (line, Some(status)) => line + " " + &status,
+            (line, None) => line,
+        };
+
+        if running.len() > 1 {
+            _ = write!(line, " (+{} more)", running.len() - 1);
+        }
+
+        match self.width {
+            Some(width) => truncate(&line, width.saturating_sub(1)),
+            None => line,
+        }
+    }
+
+    /// Returns the tree connectors drawn before the content of a row.
+    fn prefix(&self, index: usize) -> String {
+        let theme = theme();
+        let row = &self.rows[index];
+        let mut prefix = String::new();
+
+        for level in 1..=row.depth {
+            // a task at this level continues further down if one of
+            // its siblings comes before the tree returns to its parent.
+            let continues = self.rows[index + 1..]
+                .iter()
+                .take_while(|next| next.depth >= level)
+                .any(|next| next.depth == level && !next.kind.is_text());
+
+            let connector = match (level == row.depth, row.kind.is_text()) {
+                (true, true) | (false, _) if continues => theme.vertical,
+                (true, true) | (false, _) => "",
+                (true, false) if continues => theme.tee,
+                (true, false) => theme.last,
+            };
+
+            prefix += &" ".repeat(theme.margin);
+            prefix += connector;
+            prefix += &" ".repeat(theme.indent.saturating_sub(theme.margin + visible_width(connector)));
+        }
+
+        prefix
+    }
+}
+
+/// A single visible character of a line, with the escape sequences before it.
+#[derive(PartialEq, Eq)]
+struct Cell<'a> {
+    /// the escape sequences followed by the character
+    text: &'a str,
+    /// just the escape sequences
+    escapes: &'a str,
+    /// how many columns the character takes up
+    width: usize,
+}
+
+/// Splits a line into cells, ending with the escape sequences after the last character, if any.
+fn cells(line: &str) -> Vec<Cell<'_>> {
+    let mut cells = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        if c == '\x1b' {
+            // a control sequence is `ESC [`, then parameters, then a
+            // final byte from `@` to `~`. anything else after an escape
+            // is a two character sequence.
+            if chars.next().is_some_and(|(_, c)| c == '[') {
+                for (_, c) in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+
+            continue;
+        }
+
+        let end = index + c.len_utf8();
+
+        cells.push(Cell { text: &line[start..end], escapes: &line[start..index], width: c.width().unwrap_or(0) });
+        start = end;
+    }
+
+    if start < line.len() {
+        cells.push(Cell { text: &line[start..], escapes: &line[start..], width: 0 });
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::{
+        backend::AnsiBackend,
+        theme::{set_theme, Theme},
+    };
+
+    /// A writer whose bytes can be read back once the backend is done with them.
+    #[derive(Clone, Default)]
+    struct Written(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Written {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn block(height: Option<usize>) -> Block {
+        set_theme(Theme::default());
+        Block { width: Some(40), height, mode: RenderMode::Tree, ..Block::default() }
+    }
+
+    fn finished(depth: usize, text: &str) -> Row {
+        Row::new(depth, RowKind::Finished(text.to_string()))
+    }
+
+    /// Returns what the block has drawn so far as the escape sequences written to a terminal.
+    fn written(block: &mut Block) -> String {
+        let written = Written::default();
+        let mut backend = AnsiBackend::new(Box::new(written.clone()), None);
+
+        block.out.replay(&mut backend);
+        backend.flush().unwrap();
+
+        let bytes = written.0.lock().unwrap().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn connectors() {
+        let mut block = block(None);
+        block.rows = vec![
+            finished(0, "a"),
+            finished(1, "b"),
+            Row::new(2, RowKind::Text("log".into())),
+            finished(1, "c"),
+            finished(2, "d"),
+        ];
+
+        assert_eq!(block.render(0, &[]), "a");
+        assert_eq!(block.render(1, &[]), "  ┣━ b");
+        assert_eq!(block.render(2, &[]), "  ┃       log");
+        assert_eq!(block.render(3, &[]), "  ┗━ c");
+        assert_eq!(block.render(4, &[]), "       ┗━ d");
+    }
+
+    #[test]
+    fn insert_redraws_connectors_above() {
+        let mut block = block(None);
+
+        block.insert(0, finished(0, "build"), &[]);
+        assert_eq!(written(&mut block), "\r\x1b[0mbuild\x1b[K");
+
+        block.insert(1, finished(1, "compile"), &[]);
+        assert_eq!(written(&mut block), "\n\r\x1b[0m  ┗━ compile\x1b[K");
+
+        // only the connector of the row above changes, so only it is rewritten
+        block.insert(2, finished(1, "link"), &[]);
+        assert_eq!(written(&mut block), "\n\r\x1b[0m  ┗━ link\x1b[K\x1b[s\x1b[1A\x1b[3G\x1b[0m┣\x1b[0m\x1b[13G\x1b[u");
+
+        block.draw(1, &[]);
+        assert_eq!(written(&mut block), "");
+    }
+
+    #[test]
+    fn relative_mode_moves_back_down() {
+        let mut block = block(None);
+        block.mode = RenderMode::Relative;
+
+        block.insert(0, finished(0, "build"), &[]);
+        block.insert(1, finished(1, "compile"), &[]);
+        written(&mut block);
+
+        block.insert(2, finished(1, "link"), &[]);
+        assert_eq!(written(&mut block), "\n\r\x1b[0m  ┗━ link\x1b[K\x1b[1A\x1b[3G\x1b[0m┣\x1b[0m\x1b[13G\x1b[1B\x1b[10G");
+    }
+
+    #[test]
+    fn remove_redraws_rows_left_behind() {
+        let mut block = block(None);
+
+        block.insert(0, finished(0, "build"), &[]);
+        block.insert(1, finished(1, "compile"), &[]);
+        block.insert(2, finished(1, "link"), &[]);
+        written(&mut block);
+
+        block.remove(2..3, &[]);
+        assert_eq!(written(&mut block), "\r\x1b[J\x1b[1A\r  ┗━ compile\x1b[K");
+
+        block.close(&[]);
+        assert_eq!(written(&mut block), "\n");
+        assert!(block.rows.is_empty());
+    }
+
+    #[test]
+    fn rows_that_do_not_fit_are_hidden() {
+        let mut block = block(Some(3));
+
+        block.insert(0, finished(0, "r0"), &[]);
+        block.insert(1, finished(0, "r1"), &[]);
+        written(&mut block);
+
+        // a line is left free beneath the rows, so only the last two fit
+        block.insert(2, finished(0, "r2"), &[]);
+        assert_eq!(written(&mut block), "\n\r\x1b[0mr2\x1b[K\x1b[2A\r\x1b[J\rr1\x1b[K\n\rr2\x1b[K");
+        assert_eq!(block.first_drawn(), 1);
+
+        // a row above those that fit changes nothing that is drawn
+        block.insert(0, finished(0, "r"), &[]);
+        assert_eq!(written(&mut block), "");
+        assert_eq!(block.first_drawn(), 2);
+
+        // once the block ends, the whole tree is printed
+        block.close(&[]);
+        assert_eq!(written(&mut block), "\x1b[1A\r\x1b[Jr\nr0\nr1\nr2\n");
+    }
+
+    #[test]
+    fn hidden_rows_are_shown_once_they_fit() {
+        let mut block = block(Some(3));
+
+        block.insert(0, finished(0, "r0"), &[]);
+        block.insert(1, finished(0, "r1"), &[]);
+        block.insert(2, finished(0, "r2"), &[]);
+        written(&mut block);
+
+        block.remove(2..3, &[]);
+        assert_eq!(written(&mut block), "\r\x1b[J\x1b[1A\rr1\x1b[K\r\x1b[J\rr0\x1b[K\n\rr1\x1b[K");
+        assert_eq!(block.first_drawn(), 0);
+
+        block.close(&[]);
+        assert_eq!(written(&mut block), "\n");
+    }
+
+    #[test]
+    fn cells_keep_escapes_with_the_character_after_them() {
+        let line = "\x1b[32m✔\x1b[0m 日\x1b[0m";
+        let cells = cells(line);
+
+        let texts: Vec<&str> = cells.iter().map(|cell| cell.text).collect();
+        let escapes: Vec<&str> = cells.iter().map(|cell| cell.escapes).collect();
+        let widths: Vec<usize> = cells.iter().map(|cell| cell.width).collect();
+
+        assert_eq!(texts, ["\x1b[32m✔", "\x1b[0m ", "日", "\x1b[0m"]);
+        assert_eq!(escapes, ["\x1b[32m", "\x1b[0m", "", "\x1b[0m"]);
+        assert_eq!(widths, [1, 1, 2, 0]);
+    }
+}
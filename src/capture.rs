@@ -0,0 +1,43 @@
+use crate::Logger;
+
+/// Runs `f` with everything the macros draw on the current thread captured as plain text, and returns it.
+///
+/// Nothing is written to the terminal. The text is the task tree as it
+/// would be left on the screen, without colors or escape sequences, and
+/// tasks still running when `f` returns show the first frame of their
+/// spinner, so the result is the same every time:
+///
+/// ```
+/// // the charset is detected unless it is set
+/// lytelog::set_charset(lytelog::Charset::Unicode);
+///
+/// let output = lytelog::capture(|| {
+///     lytelog::start!("building");
+///     lytelog::pass!("built");
+/// });
+///
+/// # #[cfg(not(feature = "disabled"))]
+/// assert_eq!(output, "✔ built\n");
+/// ```
+///
+/// Settings like the theme, the charset and elapsed times still apply. Tasks started on
+/// other threads are not captured, unless they use the macros inside a
+/// `capture` of their own.
+pub fn capture(f: impl FnOnce()) -> String {
+    /// puts back the logger the thread used before, even if `f` panics
+    struct Restore(Option<Logger>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            Logger::set_current(self.0.take());
+        }
+    }
+
+    let logger = Logger::capturing();
+    let restore = Restore(Logger::set_current(Some(logger.clone())));
+
+    f();
+
+    drop(restore);
+    logger.captured()
+}
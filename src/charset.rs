@@ -0,0 +1,83 @@
+use std::{
+    env,
+    sync::{atomic::{AtomicU8, Ordering}, Arc, OnceLock},
+};
+
+use crate::{environment, Theme};
+
+/// The set of characters used to draw symbols and tree connectors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Charset {
+    /// Use Unicode unless `LYTELOG_STYLE` says otherwise,
+    /// or the locale or console does not support it.
+    #[default]
+    Auto,
+    /// Always use Unicode symbols and box-drawing characters.
+    Unicode,
+    /// Only use ASCII characters.
+    Ascii,
+}
+
+static CHARSET: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the characters used to draw symbols and tree connectors.
+///
+/// This chooses between the default and ASCII themes, and
+/// has no effect once a theme is set with `set_theme`.
+pub fn set_charset(charset: Charset) {
+    let value = match charset {
+        Charset::Auto => 0,
+        Charset::Unicode => 1,
+        Charset::Ascii => 2,
+    };
+
+    CHARSET.store(value, Ordering::Relaxed);
+}
+
+/// Returns the theme for the current charset.
+pub(crate) fn default_theme() -> Arc<Theme> {
+    static UNICODE: OnceLock<Arc<Theme>> = OnceLock::new();
+    static ASCII: OnceLock<Arc<Theme>> = OnceLock::new();
+    static DETECTED: OnceLock<bool> = OnceLock::new();
+
+    let unicode = match CHARSET.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => *DETECTED.get_or_init(supports_unicode),
+    };
+
+    if unicode {
+        UNICODE.get_or_init(|| Arc::new(Theme::default())).clone()
+    } else {
+        ASCII.get_or_init(|| Arc::new(Theme::ascii())).clone()
+    }
+}
+
+/// Guesses if the terminal can render Unicode output.
+fn supports_unicode() -> bool {
+    // end users can choose for themselves with LYTELOG_STYLE=ascii
+    // or LYTELOG_STYLE=unicode, whatever the terminal looks like.
+    match environment::var("LYTELOG_STYLE").map(|style| style.to_ascii_lowercase()).as_deref() {
+        Some("ascii") => return false,
+        Some("unicode") => return true,
+        _ => {}
+    }
+
+    if cfg!(windows) {
+        // the legacy console uses an OEM codepage by default,
+        // while Windows Terminal and most emulators use UTF-8.
+        return env::var_os("WT_SESSION").is_some() || env::var_os("TERM_PROGRAM").is_some();
+    }
+
+    // the first of these that is set determines the character
+    // encoding of the locale. if none are set assume UTF-8,
+    // since that is what nearly every modern terminal uses.
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .is_none_or(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
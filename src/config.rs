@@ -0,0 +1,271 @@
+use std::{fmt, io::Write, time::Duration};
+
+use crate::{
+    render::Writer, set_charset, set_color_choice, set_debounce, set_enabled, set_frame_rate, set_low_bandwidth,
+    set_max_depth, set_output_format, set_overflow, set_quiet, set_record_trace, set_render_mode, set_show_clock,
+    set_show_elapsed, set_spinner_style, set_stall_timeout, set_status_column, set_theme, set_timestamps, Charset,
+    ColorChoice, FlushGuard, Logger, OutputFormat, Overflow, RenderMode, SpinnerStyle, TerminalBackend, Theme,
+    Timestamps,
+};
+
+/// Every global setting in one place, applied together with `init`.
+///
+/// Settings that are not given are left as they are:
+///
+/// ```no_run
+/// use lytelog::{ColorChoice, Config, Theme};
+///
+/// Config::builder()
+///     .color(ColorChoice::Never)
+///     .theme(Theme::ascii())
+///     .frame_rate(10)
+///     .writer(std::io::stderr())
+///     .init();
+/// ```
+#[derive(Default)]
+#[must_use = "a config does nothing until `init` is called"]
+pub struct Config {
+    color: Option<ColorChoice>,
+    charset: Option<Charset>,
+    theme: Option<Theme>,
+    spinner: Option<SpinnerStyle>,
+    frame_rate: Option<u32>,
+    low_bandwidth: Option<bool>,
+    overflow: Option<Overflow>,
+    render_mode: Option<RenderMode>,
+    max_depth: Option<usize>,
+    quiet: Option<bool>,
+    enabled: Option<bool>,
+    output_format: Option<OutputFormat>,
+    show_elapsed: Option<bool>,
+    show_clock: Option<bool>,
+    status_column: Option<bool>,
+    timestamps: Option<Timestamps>,
+    debounce: Option<Duration>,
+    stall_timeout: Option<Option<Duration>>,
+    record_trace: Option<bool>,
+    writer: Option<Writer>,
+}
+
+impl Config {
+    /// Starts a config that changes nothing.
+    pub fn builder() -> Self {
+        Self::default()
+    }
+
+    /// Sets when color is used, like `set_color_choice`.
+    pub fn color(self, color: ColorChoice) -> Self {
+        Self { color: Some(color), ..self }
+    }
+
+    /// Sets the characters symbols are drawn with, like `set_charset`.
+    pub fn charset(self, charset: Charset) -> Self {
+        Self { charset: Some(charset), ..self }
+    }
+
+    /// Sets the theme, like `set_theme`.
+    pub fn theme(self, theme: Theme) -> Self {
+        Self { theme: Some(theme), ..self }
+    }
+
+    /// Sets the spinner of every task, like `set_spinner_style`.
+    pub fn spinner(self, spinner: SpinnerStyle) -> Self {
+        Self { spinner: Some(spinner), ..self }
+    }
+
+    /// Limits how often spinners are redrawn, like `set_frame_rate`.
+    pub fn frame_rate(self, frames_per_second: u32) -> Self {
+        Self { frame_rate: Some(frames_per_second), ..self }
+    }
+
+    /// Sets whether output is batched for laggy remote sessions, like `set_low_bandwidth`.
+    pub fn low_bandwidth(self, enabled: bool) -> Self {
+        Self { low_bandwidth: Some(enabled), ..self }
+    }
+
+    /// Sets what happens to running tasks that are too wide, like `set_overflow`.
+    pub fn overflow(self, overflow: Overflow) -> Self {
+        Self { overflow: Some(overflow), ..self }
+    }
+
+    /// Sets how running tasks are drawn, like `set_render_mode`.
+    pub fn render_mode(self, mode: RenderMode) -> Self {
+        Self { render_mode: Some(mode), ..self }
+    }
+
+    /// Sets how many levels of tasks are drawn, like `set_max_depth`.
+    pub fn max_depth(self, depth: usize) -> Self {
+        Self { max_depth: Some(depth), ..self }
+    }
+
+    /// Sets whether only warnings and failures are printed, like `set_quiet`.
+    pub fn quiet(self, quiet: bool) -> Self {
+        Self { quiet: Some(quiet), ..self }
+    }
+
+    /// Sets whether anything is printed at all, like `set_enabled`.
+    pub fn enabled(self, enabled: bool) -> Self {
+        Self { enabled: Some(enabled), ..self }
+    }
+
+    /// Sets how task events are written, like `set_output_format`.
+    pub fn output_format(self, format: OutputFormat) -> Self {
+        Self { output_format: Some(format), ..self }
+    }
+
+    /// Sets whether completed tasks show how long they ran, like `set_show_elapsed`.
+    pub fn show_elapsed(self, show: bool) -> Self {
+        Self { show_elapsed: Some(show), ..self }
+    }
+
+    /// Sets whether running tasks show a live clock, like `set_show_clock`.
+    pub fn show_clock(self, show: bool) -> Self {
+        Self { show_clock: Some(show), ..self }
+    }
+
+    /// Sets whether running tasks have a status column, like `set_status_column`.
+    pub fn status_column(self, show: bool) -> Self {
+        Self { status_column: Some(show), ..self }
+    }
+
+    /// Sets what the line each task ends with is prefixed with, like `set_timestamps`.
+    pub fn timestamps(self, timestamps: Timestamps) -> Self {
+        Self { timestamps: Some(timestamps), ..self }
+    }
+
+    /// Sets how long tasks run before their spinner is drawn, like `set_debounce`.
+    pub fn debounce(self, threshold: Duration) -> Self {
+        Self { debounce: Some(threshold), ..self }
+    }
+
+    /// Sets when tasks without activity are shown as stalled, like `set_stall_timeout`.
+    pub fn stall_timeout(self, timeout: Option<Duration>) -> Self {
+        Self { stall_timeout: Some(timeout), ..self }
+    }
+
+    /// Sets whether tasks are recorded for `export_trace`, like `set_record_trace`.
+    pub fn record_trace(self, enabled: bool) -> Self {
+        Self { record_trace: Some(enabled), ..self }
+    }
+
+    /// Makes the macros draw to a writer instead of stdout.
+    ///
+    /// Like `Logger::with_writer`, the writer is not treated as a
+    /// terminal, so rows are never cut to fit.
+    pub fn writer(self, writer: impl Write + Send + 'static) -> Self {
+        Self { writer: Some(Writer::Other(Box::new(writer))), ..self }
+    }
+
+    /// Makes the macros draw through a backend, like `Logger::with_backend`.
+    ///
+    /// This replaces the writer, and the other way around.
+    pub fn backend(self, backend: impl TerminalBackend + 'static) -> Self {
+        Self { writer: Some(Writer::Backend(Box::new(backend))), ..self }
+    }
+
+    /// Applies every setting that was given.
+    ///
+    /// Nothing is drawn while the settings change, so a spinner that is
+    /// already turning never shows a mix of old and new settings. This
+    /// is meant to be called before the first task starts, since the
+    /// writer can only be changed until the macros first draw something.
+    pub fn init(self) {
+        let global = Logger::global();
+
+        global.between_frames(|| {
+            // the charset chooses the theme, so it is set first
+            if let Some(charset) = self.charset {
+                set_charset(charset);
+            }
+
+            if let Some(theme) = self.theme {
+                set_theme(theme);
+            }
+
+            if let Some(color) = self.color {
+                set_color_choice(color);
+            }
+
+            if let Some(spinner) = self.spinner {
+                set_spinner_style(spinner);
+            }
+
+            if let Some(frame_rate) = self.frame_rate {
+                set_frame_rate(frame_rate);
+            }
+
+            if let Some(enabled) = self.low_bandwidth {
+                set_low_bandwidth(enabled);
+            }
+
+            if let Some(overflow) = self.overflow {
+                set_overflow(overflow);
+            }
+
+            if let Some(mode) = self.render_mode {
+                set_render_mode(mode);
+            }
+
+            if let Some(depth) = self.max_depth {
+                set_max_depth(depth);
+            }
+
+            if let Some(quiet) = self.quiet {
+                set_quiet(quiet);
+            }
+
+            if let Some(enabled) = self.enabled {
+                set_enabled(enabled);
+            }
+
+            if let Some(format) = self.output_format {
+                set_output_format(format);
+            }
+
+            if let Some(show) = self.show_elapsed {
+                set_show_elapsed(show);
+            }
+
+            if let Some(show) = self.show_clock {
+                set_show_clock(show);
+            }
+
+            if let Some(show) = self.status_column {
+                set_status_column(show);
+            }
+
+            if let Some(timestamps) = self.timestamps {
+                set_timestamps(timestamps);
+            }
+
+            if let Some(threshold) = self.debounce {
+                set_debounce(threshold);
+            }
+
+            if let Some(timeout) = self.stall_timeout {
+                set_stall_timeout(timeout);
+            }
+
+            if let Some(enabled) = self.record_trace {
+                set_record_trace(enabled);
+            }
+
+            if let Some(writer) = self.writer {
+                global.set_writer(writer);
+            }
+        });
+    }
+
+    /// Applies every setting like `init`, returning a guard that calls
+    /// `shutdown` once it is dropped, usually at the end of `main`.
+    pub fn init_guarded(self) -> FlushGuard {
+        self.init();
+        FlushGuard::new()
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config").finish_non_exhaustive()
+    }
+}
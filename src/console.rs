@@ -0,0 +1,85 @@
+use crate::{event::TaskEvent, format, style::strip_ansi, Outcome};
+
+/// Writes an event to the browser console.
+///
+/// Every task opens a `console.group` that its subtasks and log lines
+/// go into, and closes it again when it ends, followed by its outcome.
+/// Warnings and failures are written with `console.warn` and `console.error`,
+/// so the browser highlights them. Since the console can only close the
+/// group opened last, tasks that end out of order close the wrong group.
+pub(crate) fn write(event: &TaskEvent) {
+    match event {
+        TaskEvent::Start { message, .. } => group(&strip_ansi(message)),
+        TaskEvent::Update { message, .. } | TaskEvent::Log { message, .. } => log(&strip_ansi(message)),
+        TaskEvent::End { id, symbol, message, elapsed, .. } => {
+            if id.is_some() {
+                group_end();
+            }
+
+            let mut line = format!("{} {}", strip_ansi(&symbol.render()), strip_ansi(message));
+
+            if let Some(elapsed) = elapsed {
+                line += &format!(" ({})", format::duration(*elapsed));
+            }
+
+            match symbol.outcome() {
+                Outcome::Warn => warn(&line),
+                Outcome::Fail => error(&line),
+                Outcome::Pass | Outcome::Skip => log(&line),
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn group(text: &str) {
+    web_sys::console::group_1(&text.into());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn group_end() {
+    web_sys::console::group_end();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn log(text: &str) {
+    web_sys::console::log_1(&text.into());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn warn(text: &str) {
+    web_sys::console::warn_1(&text.into());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn error(text: &str) {
+    web_sys::console::error_1(&text.into());
+}
+
+// outside the browser there is no console to call into, so the
+// groups are written to stdout, indented like the console shows them.
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+    pub fn group(text: &str) {
+        log(text);
+        DEPTH.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn group_end() {
+        _ = DEPTH.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |depth| depth.checked_sub(1));
+    }
+
+    pub fn log(text: &str) {
+        println!("{}{text}", "  ".repeat(DEPTH.load(Ordering::Relaxed)));
+    }
+
+    pub use log as warn;
+    pub use log as error;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+use native::{error, group, group_end, log, warn};
@@ -0,0 +1,21 @@
+use std::env;
+
+/// Returns the value of an environment variable, if it is set and not empty.
+pub(crate) fn var(name: &str) -> Option<String> {
+    env::var(name).ok().map(|value| value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+/// Reads an environment variable as a switch, so `LYTELOG_QUIET=1` turns
+/// something on and `LYTELOG_QUIET=0` turns it off. Other values are ignored.
+pub(crate) fn flag(name: &str) -> Option<bool> {
+    match var(name)?.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Returns true if `TERM` says the terminal understands no escape sequences.
+pub(crate) fn is_dumb_terminal() -> bool {
+    var("TERM").is_some_and(|term| term == "dumb")
+}
@@ -0,0 +1,132 @@
+use std::{
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+use crate::{output, summary, tee, trace, Symbol};
+
+type Hook = Arc<dyn Fn(&TaskEvent) + Send + Sync>;
+
+static HOOKS: RwLock<Vec<Hook>> = RwLock::new(Vec::new());
+
+static SUBSCRIBERS: Mutex<Vec<Sender<TaskEvent>>> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// set while a hook is called on this thread
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Something that happened to a task, as passed to the hooks added with `on_event`.
+///
+/// With the `serde` feature, events can be serialized and deserialized.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum TaskEvent {
+    /// A task started, as a subtask of `parent` if it has one, with the fields given to `start!`.
+    #[non_exhaustive]
+    Start { id: u64, parent: Option<u64>, depth: usize, message: String, fields: Vec<(String, String)> },
+    /// The message of a running task was replaced.
+    #[non_exhaustive]
+    Update { id: u64, depth: usize, message: String },
+    /// A line was printed with `log!` or `detail!`, under a task if `id` is set.
+    #[non_exhaustive]
+    Log { id: Option<u64>, depth: usize, message: String },
+    /// A task ended, or an outcome was printed while no task was running if `id` is not set.
+    #[non_exhaustive]
+    End {
+        id: Option<u64>,
+        depth: usize,
+        symbol: Symbol,
+        message: String,
+        elapsed: Option<Duration>,
+        fields: Vec<(String, String)>,
+    },
+}
+
+/// Calls `hook` for everything that happens to a task from now on, of every logger.
+///
+/// This plugs side effects into the lifecycle of tasks without wrapping
+/// every macro, like a desktop notification when something fails:
+///
+/// ```no_run
+/// use lytelog::{Outcome, TaskEvent};
+///
+/// lytelog::on_event(|event| {
+///     if let TaskEvent::End { symbol, message, .. } = event {
+///         if symbol.outcome() == Outcome::Fail {
+///             eprintln!("\x07{message}");
+///         }
+///     }
+/// });
+/// ```
+///
+/// Hooks are called in the order they were added, on the thread the event
+/// happened on, before the macro that caused it returns. They are called once
+/// lytelog has let go of its tasks, so a hook can start, update and end tasks
+/// or log lines itself. A hook that panics only misses that event, and the
+/// others are still called. Slow work like sending a webhook belongs on another thread.
+pub fn on_event(hook: impl Fn(&TaskEvent) + Send + Sync + 'static) {
+    HOOKS.write().unwrap().push(Arc::new(hook));
+}
+
+/// Returns a channel that receives everything that happens to a task from now on, of every logger.
+///
+/// Tasks are drawn as usual while the events are received, so a GUI or a
+/// daemon can mirror the task tree somewhere else, and with the `serde`
+/// feature the events can be sent over a socket as they are:
+///
+/// ```no_run
+/// let events = lytelog::subscribe();
+///
+/// std::thread::spawn(move || {
+///     for event in events {
+///         println!("{event:?}");
+///     }
+/// });
+/// ```
+///
+/// Events are sent without waiting for them to be received, and once the
+/// receiver is dropped, nothing is sent to it anymore.
+pub fn subscribe() -> Receiver<TaskEvent> {
+    let (sender, receiver) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap().push(sender);
+    receiver
+}
+
+/// Returns true while a hook is called on this thread, whose panics are caught.
+pub(crate) fn in_hook() -> bool {
+    IN_HOOK.get()
+}
+
+/// Records an event to structured output, the tee file, the summary, the trace, the hooks and the subscribers.
+pub(crate) fn dispatch(event: TaskEvent) {
+    if output::is_json() {
+        output::write_json(&event);
+    }
+
+    #[cfg(feature = "wasm")]
+    if output::is_console() {
+        crate::console::write(&event);
+    }
+
+    tee::write(&event);
+    summary::record(&event);
+    trace::record(&event);
+
+    // the hooks are called without holding on to the list, so a hook can add another
+    let hooks = HOOKS.read().unwrap().clone();
+
+    for hook in hooks {
+        let outer = IN_HOOK.replace(true);
+        _ = panic::catch_unwind(AssertUnwindSafe(|| hook(&event)));
+        IN_HOOK.set(outer);
+    }
+
+    SUBSCRIBERS.lock().unwrap().retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
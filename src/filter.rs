@@ -0,0 +1,67 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+    OnceLock,
+};
+
+use crate::environment;
+
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(usize::MAX);
+static QUIET: AtomicU8 = AtomicU8::new(0);
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Sets whether lytelog prints anything at all.
+///
+/// While disabled, starting a task records nothing and returns a handle
+/// that does nothing, and the macros do not even format their messages,
+/// so libraries can be instrumented without forcing output on the programs
+/// that use them. Tasks that were already running can still be ended
+/// through their handles.
+/// The `disabled` feature disables lytelog for good at compile time, and
+/// the macros then compile to nothing but a check of their arguments' types.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true unless lytelog has been disabled.
+pub(crate) fn is_enabled() -> bool {
+    !cfg!(feature = "disabled") && ENABLED.load(Ordering::Relaxed)
+}
+
+/// Sets how many levels of tasks are drawn.
+///
+/// With `set_max_depth(2)`, tasks and their subtasks are drawn but
+/// anything nested more deeply is not. The outcomes of the hidden tasks
+/// roll up into their nearest drawn ancestor, which ends with a warning or
+/// failure if any of them did. The depth is at least 1.
+pub fn set_max_depth(depth: usize) {
+    MAX_DEPTH.store(depth.max(1), Ordering::Relaxed);
+}
+
+/// Returns true if a task at a depth is drawn, where 0 is the outermost task.
+pub(crate) fn is_visible(depth: usize) -> bool {
+    depth < MAX_DEPTH.load(Ordering::Relaxed)
+}
+
+/// Sets whether only warnings and failures are printed.
+///
+/// In quiet mode nothing is drawn while tasks run, and tasks that pass
+/// or are skipped print nothing. A task that warns or fails prints the
+/// messages of its ancestors first, so the context of the problem is
+/// still there, but without any spinners or cursor movement.
+///
+/// Until this is called, quiet mode follows the `LYTELOG_QUIET`
+/// environment variable, so `LYTELOG_QUIET=1` turns it on.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(if quiet { 1 } else { 2 }, Ordering::Relaxed);
+}
+
+/// Returns true if quiet mode is enabled.
+pub(crate) fn is_quiet() -> bool {
+    static FROM_ENV: OnceLock<bool> = OnceLock::new();
+
+    match QUIET.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => *FROM_ENV.get_or_init(|| environment::flag("LYTELOG_QUIET").unwrap_or(false)),
+    }
+}
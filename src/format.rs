@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use crate::time::{SystemTime, UNIX_EPOCH};
+
+/// Formats a byte count with decimal units, e.g. `3.4 MB`.
+pub(crate) fn bytes(count: f64) -> String {
+    const UNITS: [&str; 5] = ["kB", "MB", "GB", "TB", "PB"];
+
+    if count < 1000.0 {
+        return format!("{} B", count.max(0.0) as u64);
+    }
+
+    let mut value = count / 1000.0;
+    let mut unit = 0;
+
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Formats a duration as a clock, e.g. `03:07` or `1:03:07`.
+pub(crate) fn clock(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
+
+/// Formats a duration as a clock with milliseconds, e.g. `00:03.214` or `1:00:03.214`.
+pub(crate) fn offset(duration: Duration) -> String {
+    format!("{}.{:03}", clock(duration), duration.subsec_millis())
+}
+
+/// Formats the time of day of a time in UTC, with milliseconds, e.g. `13:37:00.250`.
+pub(crate) fn time_of_day(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() % 86400;
+
+    format!("{:02}:{:02}:{:02}.{:03}", seconds / 3600, seconds / 60 % 60, seconds % 60, since_epoch.subsec_millis())
+}
+
+/// Formats a short duration for humans, e.g. `120ms`, `3.2s` or `1m 05s`.
+pub(crate) fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else if seconds < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else if seconds < 3600 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h {:02}m", seconds / 3600, seconds / 60 % 60)
+    }
+}
+
+/// Formats a duration down to nanoseconds, for timing short work, e.g. `850ns`, `12.4µs` or `1.21ms`.
+pub(crate) fn precise(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+
+    if nanos < 1_000 {
+        format!("{nanos}ns")
+    } else if nanos < 1_000_000 {
+        format!("{:.1}µs", nanos as f64 / 1e3)
+    } else if nanos < 1_000_000_000 {
+        format!("{:.2}ms", nanos as f64 / 1e6)
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+/// Formats a duration in whole seconds, e.g. `30s` or `1m 05s`.
+pub(crate) fn seconds(elapsed: Duration) -> String {
+    match elapsed.as_secs() {
+        seconds @ 0..60 => format!("{seconds}s"),
+        _ => duration(elapsed),
+    }
+}
+
+/// Formats the fields of a task as `key=value` pairs, quoting values that
+/// are empty or have spaces, quotes or `=` in them, e.g. `file="my notes.txt" size=42`.
+pub(crate) fn fields(fields: &[(String, String)]) -> String {
+    let pairs: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| {
+            if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+                format!("{key}={value:?}")
+            } else {
+                format!("{key}={value}")
+            }
+        })
+        .collect();
+
+    pairs.join(" ")
+}
+
+/// Formats a time as an RFC 3339 UTC timestamp, e.g. `2024-05-01T13:37:00.250Z`.
+pub(crate) fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, time_of_day) = (seconds / 86400, seconds % 86400);
+
+    // converts days since the epoch to a civil date, from
+    // http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
@@ -0,0 +1,100 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{Logger, TaskHandle};
+
+/// Runs futures as tasks.
+pub trait FutureExt: Future + Sized {
+    /// Runs the future as a task, which starts when the future is first polled
+    /// and passes or fails along with the `Result` the future resolves to.
+    ///
+    /// ```no_run
+    /// use lytelog::FutureExt as _;
+    ///
+    /// # async fn fetch(_: &str) -> std::io::Result<String> { Ok(String::new()) }
+    /// # async fn run() -> std::io::Result<()> {
+    /// let manifest = fetch("manifest.json").spin("fetching manifest").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Many futures can run as tasks at once, on any number of threads, each
+    /// on its own row. Tasks started with `start!` while the future is polled
+    /// are subtasks of its task, and so is a future that is first polled by it.
+    /// A future that is dropped before it resolves is cancelled.
+    fn spin(self, message: impl Into<String>) -> Spin<Self>;
+}
+
+impl<F, T, E> FutureExt for F
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    fn spin(self, message: impl Into<String>) -> Spin<Self> {
+        Spin { future: Box::pin(self), message: message.into(), logger: Logger::current(), task: None }
+    }
+}
+
+/// A future that runs as a task, returned by `FutureExt::spin`.
+#[must_use = "futures do nothing unless awaited"]
+pub struct Spin<F> {
+    future: Pin<Box<F>>,
+    message: String,
+    /// the logger the task is started on, which is the one the macros used where the future was created
+    logger: Logger,
+    /// the task and the id of its context, once the future has been polled
+    task: Option<(TaskHandle, u64)>,
+}
+
+impl<F, T, E> Future for Spin<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let (task, context) = this.task.get_or_insert_with(|| this.logger.start_polled(this.message.clone()));
+
+        // the context is left again even if the future panics
+        this.logger.reenter(*context, task.id);
+        let polling = Polling { logger: &this.logger, context: *context };
+        let result = this.future.as_mut().poll(cx);
+        drop(polling);
+
+        let Poll::Ready(result) = result else { return Poll::Pending };
+        let (task, _) = this.task.take().unwrap();
+
+        match &result {
+            Ok(_) => task.pass(this.message.clone()),
+            Err(error) => task.fail(format!("{}: {error}", this.message)),
+        }
+
+        Poll::Ready(result)
+    }
+}
+
+impl<F> Drop for Spin<F> {
+    fn drop(&mut self) {
+        if let Some((task, _)) = self.task.take() {
+            task.cancel(format!("{} (cancelled)", self.message));
+        }
+    }
+}
+
+/// Leaves the context of a future once it has been polled.
+struct Polling<'a> {
+    logger: &'a Logger,
+    context: u64,
+}
+
+impl Drop for Polling<'_> {
+    fn drop(&mut self) {
+        self.logger.leave(self.context)
+    }
+}
@@ -1,21 +1,317 @@
-use std::{
-    io::{self, Write},
-    sync::{atomic::{AtomicBool, Ordering}, Mutex}, thread, time::Duration
+mod backend;
+mod block;
+mod capture;
+mod charset;
+mod config;
+#[cfg(feature = "wasm")]
+mod console;
+mod environment;
+mod event;
+mod filter;
+mod format;
+#[cfg(feature = "async")]
+mod future;
+#[cfg(feature = "log")]
+mod log_backend;
+mod logger;
+mod measure;
+mod output;
+mod panic_hook;
+mod process;
+mod render;
+mod retry;
+mod shutdown;
+#[cfg(feature = "signals")]
+mod signals;
+mod spinner;
+mod style;
+mod summary;
+mod symbol;
+mod task;
+mod tee;
+mod terminal;
+mod theme;
+mod time;
+mod trace;
+#[cfg(feature = "tracing")]
+mod tracing_layer;
+
+use std::{borrow::BorrowMut, error::Error, fmt, io, process::{Command, ExitStatus}};
+
+use filter::is_enabled;
+
+#[cfg(feature = "crossterm")]
+pub use backend::CrosstermBackend;
+#[cfg(feature = "serial")]
+pub use backend::SerialBackend;
+pub use backend::TerminalBackend;
+pub use block::{set_overflow, set_render_mode, Overflow, RenderMode};
+pub use capture::capture;
+pub use charset::{set_charset, Charset};
+pub use config::Config;
+pub use event::{on_event, subscribe, TaskEvent};
+pub use filter::{set_enabled, set_max_depth, set_quiet};
+#[cfg(feature = "async")]
+pub use future::{FutureExt, Spin};
+#[cfg(feature = "log")]
+pub use log_backend::init_log;
+pub use logger::Logger;
+pub use measure::Measurement;
+pub use output::{set_output_format, OutputFormat};
+pub use panic_hook::install_panic_hook;
+pub use render::{set_frame_rate, set_low_bandwidth};
+pub use shutdown::{shutdown, FlushGuard};
+#[cfg(feature = "signals")]
+pub use signals::handle_signals;
+pub use spinner::{set_spinner_style, SpinnerStyle};
+pub use style::{set_color_choice, style, Color, ColorChoice, Styled};
+pub use summary::{finish, summary, RunResult};
+pub use symbol::Symbol;
+pub use task::{
+    set_debounce, set_show_clock, set_show_elapsed, set_stall_timeout, set_status_column, set_timestamps, RunningState,
+    TaskContext, TaskHandle, TaskInfo, TaskState, Timestamps,
 };
+pub use tee::tee_to_file;
+pub use trace::{export_trace, set_record_trace};
+pub use theme::{set_theme, Glyph, Theme};
+#[cfg(feature = "tracing")]
+pub use tracing_layer::TaskLayer;
 
-#[derive(Clone, Copy, Debug)]
-struct Task {
-    pub row_offset: i32,
+#[doc(hidden)]
+pub use task::{Group, ProgressStyle, TaskOptions};
+
+/// The final state of a task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+    /// The task was short-circuited, e.g. by a cache hit.
+    Skip,
 }
 
-static TASKS: Mutex<Vec<Task>> = Mutex::new(Vec::new());
-static SPINNING: AtomicBool = AtomicBool::new(false);
+impl Outcome {
+    /// Returns how bad an outcome is, from skipped to failed.
+    fn severity(self) -> u8 {
+        match self {
+            Outcome::Skip => 0,
+            Outcome::Pass => 1,
+            Outcome::Warn => 2,
+            Outcome::Fail => 3,
+        }
+    }
+
+    /// Returns the worse of two outcomes.
+    fn worst(self, other: Outcome) -> Outcome {
+        if other.severity() > self.severity() {
+            other
+        } else {
+            self
+        }
+    }
 
-/// Load a task or subtask with a spinner
+    /// Returns the lowercase name of the outcome, e.g. `"pass"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "fail",
+            Outcome::Skip => "skip",
+        }
+    }
+}
+
+/// Load a task or subtask with a spinner, returning its `TaskHandle`.
+///
+/// A spinner style may be given for just this task with
+/// `start!(spinner = style, "message")`, and an icon shown before its
+/// message, both while it runs and once it ends, with
+/// `start!(icon = "📦", "packaging {}", name)`.
+///
+/// A task given a name, as in `start!(name: "db-migrate", "running migrations")`,
+/// can be ended anywhere by that name, without its handle, with
+/// `pass!(name: "db-migrate", "{count} migrations applied")`. The other
+/// macros that end tasks and `update!` take a name the same way, and
+/// `named` returns the handle of a task by its name.
+///
+/// Fields for tools that read the output are given in braces after the
+/// message, as in `start!("uploading {name}", { file = path.display(), size = bytes })`.
+/// They are shown dimmed after the message as `file=… size=…`, and
+/// written as they are to JSON output and the tee file.
 #[macro_export]
 macro_rules! start {
+    (@options [$($options:tt)*] spinner = $spinner:expr, $($tokens:tt)*) => {
+        $crate::start!(@options [$($options)* spinner: Some($spinner),] $($tokens)*)
+    };
+    (@options [$($options:tt)*] icon = $icon:expr, $($tokens:tt)*) => {
+        $crate::start!(@options [$($options)* icon: Some(::std::string::ToString::to_string(&$icon)),] $($tokens)*)
+    };
+    (@options [$($options:tt)*] name: $name:expr, $($tokens:tt)*) => {
+        $crate::start!(@options [$($options)* name: Some(::std::string::ToString::to_string(&$name)),] $($tokens)*)
+    };
+    (@options [$($options:tt)*] $message:literal, { $($key:ident = $value:expr),* $(,)? }) => {
+        $crate::__if_enabled__!($crate::__start_task__(format_args!($message), $crate::TaskOptions {
+            $($options)*
+            fields: vec![$((
+                ::std::string::ToString::to_string(stringify!($key)),
+                ::std::string::ToString::to_string(&$value),
+            )),*],
+            ..Default::default()
+        }), $crate::__untracked__())
+    };
+    (@options [$($options:tt)*] $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__start_task__(format_args!($($tokens)*), $crate::TaskOptions {
+            $($options)*
+            ..Default::default()
+        }), $crate::__untracked__())
+    };
+    ($($tokens:tt)*) => {
+        $crate::start!(@options [] $($tokens)*)
+    };
+}
+
+/// Load a task with a progress bar next to its message.
+///
+/// Progress is reported through the returned `TaskHandle`
+/// with `set_progress` or `inc`.
+#[macro_export]
+macro_rules! progress {
+    ($($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__start_task__(format_args!($($tokens)*), $crate::TaskOptions {
+            progress: Some($crate::ProgressStyle::Bar),
+            length: 100,
+            ..Default::default()
+        }), $crate::__untracked__())
+    };
+}
+
+/// Load a task with a live `(n/total)` counter after its message.
+///
+/// The count is advanced with `tick` on the returned `TaskHandle`.
+/// Formatted messages put the total first, as in
+/// `start_counted!(total = files.len(), "processing {}", dir)`.
+#[macro_export]
+macro_rules! start_counted {
+    (total = $total:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__start_task__(format_args!($($tokens)*), $crate::TaskOptions {
+            progress: Some($crate::ProgressStyle::Counter),
+            length: $total as u64,
+            ..Default::default()
+        }), $crate::__untracked__())
+    };
+    ($message:literal, $total:expr $(,)?) => {
+        $crate::start_counted!(total = $total, $message)
+    };
+}
+
+/// Load a task that fails on its own if it has not ended in time.
+///
+/// Once the timeout passes, the task and any subtasks it still has
+/// running are failed, the task with `(timed out after 60s)` appended to
+/// its message, so what is printed after it stays in order. Formatted
+/// messages put the timeout first, as in
+/// `start_with_timeout!(timeout = Duration::from_secs(60), "waiting for {}", host)`.
+#[macro_export]
+macro_rules! start_with_timeout {
+    (timeout = $timeout:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__start_task__(format_args!($($tokens)*), $crate::TaskOptions {
+            timeout: Some($timeout),
+            ..Default::default()
+        }), $crate::__untracked__())
+    };
+    ($message:literal, $timeout:expr $(,)?) => {
+        $crate::start_with_timeout!(timeout = $timeout, $message)
+    };
+}
+
+/// Load a task that transfers a number of bytes.
+///
+/// The task shows the bytes transferred, the throughput and
+/// the estimated time remaining as `advance` is called on the
+/// returned `TaskHandle`. Formatted messages put the total first, as in
+/// `transfer!(total = size, "downloading {}", url)`.
+#[macro_export]
+macro_rules! transfer {
+    (total = $total:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__start_task__(format_args!($($tokens)*), $crate::TaskOptions {
+            progress: Some($crate::ProgressStyle::Bytes),
+            length: $total as u64,
+            ..Default::default()
+        }), $crate::__untracked__())
+    };
+    ($message:literal, $total:expr $(,)?) => {
+        $crate::transfer!(total = $total, $message)
+    };
+}
+
+/// Replaces the message of the most recently created task
+/// without completing it.
+#[macro_export]
+macro_rules! update {
+    (name: $name:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__update_named__(&$name, format_args!($($tokens)*)), ())
+    };
     ($($tokens:tt)*) => {
-        $crate::__start_task__(format!($($tokens)*));
+        $crate::__if_enabled__!($crate::__update_task__(None, format_args!($($tokens)*)), ())
+    };
+}
+
+/// Prints a line without disturbing running tasks.
+///
+/// While a task is running the line is printed beneath it,
+/// indented to where its subtasks begin.
+#[macro_export]
+macro_rules! log {
+    ($($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__log__(format_args!($($tokens)*)), ())
+    };
+}
+
+/// Prints a line to stdout like `println!`, without disturbing running tasks.
+///
+/// The running tasks are erased while the line is printed and drawn again
+/// beneath it, so output of the application scrolls above them instead of
+/// being drawn over. Using this in place of every `println!` keeps the
+/// output of the whole program clear of the tree:
+///
+/// ```
+/// lytelog::start!("resolving dependencies");
+/// lytelog::out!("using registry {}", "crates.io");
+/// lytelog::pass!("resolved");
+/// ```
+#[macro_export]
+macro_rules! out {
+    () => {
+        $crate::__out__(format_args!(""))
+    };
+    ($($tokens:tt)*) => {
+        $crate::__out__(format_args!($($tokens)*))
+    };
+}
+
+/// Prints a line to stderr like `eprintln!`, without disturbing running tasks, like `out!`.
+#[macro_export]
+macro_rules! eout {
+    () => {
+        $crate::__eout__(format_args!(""))
+    };
+    ($($tokens:tt)*) => {
+        $crate::__eout__(format_args!($($tokens)*))
+    };
+}
+
+/// Prints supplementary output, like compiler messages or diff hunks, beneath the most recently created task.
+///
+/// Every line is printed as it is, indented to where the subtasks of the
+/// task begin, with tabs expanded so the tree stays aligned. Unlike with
+/// `log!`, the lines count as activity of the task, so a task that streams
+/// output is not shown as stalled while it does. Use `TaskHandle::detail` to print
+/// beneath a task other than the innermost.
+#[macro_export]
+macro_rules! detail {
+    ($($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__detail__(format_args!($($tokens)*)), ())
     };
 }
 
@@ -23,8 +319,11 @@ macro_rules! start {
 /// replaces the spinner with a green check mark.
 #[macro_export]
 macro_rules! pass {
+    (name: $name:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__end_named__(&$name, $crate::Outcome::Pass, format_args!($($tokens)*)), ());
+    };
     ($($tokens:tt)*) => {
-        $crate::__end_task__("\x1b[32;1m✔\x1b[0m", format!($($tokens)*));
+        $crate::__if_enabled__!($crate::__end_task__($crate::Outcome::Pass, format_args!($($tokens)*)), ());
     };
 }
 
@@ -32,169 +331,397 @@ macro_rules! pass {
 /// and replaces the spinner with a hazard.
 #[macro_export]
 macro_rules! warn {
+    (name: $name:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__end_named__(&$name, $crate::Outcome::Warn, format_args!($($tokens)*)), ());
+    };
     ($($tokens:tt)*) => {
-        $crate::__end_task__("\x1b[33;1m⚠\x1b[0m", format!($($tokens)*));
+        $crate::__if_enabled__!($crate::__end_task__($crate::Outcome::Warn, format_args!($($tokens)*)), ());
     };
 }
 
 /// Indicates that the most recently created task has failed
 /// and replaces the spinner with a red x.
+///
+/// With `fail!(error = err, "message")` the error and each of its sources are
+/// printed beneath the task as `caused by:` lines. Without a message, as in
+/// `fail!(error = err)`, the error itself is the message. An `anyhow::Error`
+/// is passed as `fail!(error = &*err)`.
 #[macro_export]
 macro_rules! fail {
+    (name: $name:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__end_named__(&$name, $crate::Outcome::Fail, format_args!($($tokens)*)), ())
+    };
+    (error = $error:expr $(,)?) => {
+        $crate::__if_enabled__!($crate::__fail_with__(&$error, None), ())
+    };
+    (error = $error:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__fail_with__(&$error, Some(format_args!($($tokens)*))), ())
+    };
     ($($tokens:tt)*) => {
-        $crate::__end_task__("\x1b[31;1m𝕩\x1b[0m", format!($($tokens)*))
+        $crate::__if_enabled__!($crate::__end_task__($crate::Outcome::Fail, format_args!($($tokens)*)), ())
     };
 }
 
-#[doc(hidden)]
-pub fn __start_task__(message: String) {
-    // this will never panic since mutex locks can only
-    // fail if the thread holding the lock panics.
-    // this is guarenteed as long as:
-    //      1. TASKS is never locked outside of lytlog
-    //      2. lytlog code never panics
-    // so long as these two invariants are satisfied
-    // (and they are by design) then locks of TASKS
-    // will not panic.
-
-    let mut tasks = TASKS.lock().unwrap();
-
-    if tasks.len() > 0 {
-        // adjust the offset (from bottom row) of each task
-        for task in tasks.iter_mut() {
-            task.row_offset += 1;
-        }
+/// Ends the most recently created task with any `Symbol`, as in
+/// `end!(Symbol::custom("⏩", Color::Cyan), "fast-forwarded {}", name)`.
+#[macro_export]
+macro_rules! end {
+    (name: $name:expr, $symbol:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__end_named__(&$name, $symbol, format_args!($($tokens)*)), ())
+    };
+    ($symbol:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__end_task__($symbol, format_args!($($tokens)*)), ())
+    };
+}
 
-        println!()
-    }
+/// Indicates that the most recently created task was skipped
+/// and replaces the spinner with a gray arrow.
+#[macro_export]
+macro_rules! skip {
+    (name: $name:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__end_named__(&$name, $crate::Outcome::Skip, format_args!($($tokens)*)), ())
+    };
+    ($($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__end_task__($crate::Outcome::Skip, format_args!($($tokens)*)), ())
+    };
+}
 
-    if let Some(last_row) = tasks.last().map(|task| task.row_offset) {
-        print!("\x1b[s");
+/// Indicates that the most recently created task was cancelled on purpose
+/// and replaces the spinner with a gray circle, as do its running subtasks,
+/// so the stack unwinds to the task.
+///
+/// ```
+/// lytelog::start!("deploying");
+/// lytelog::start!("uploading assets");
+/// lytelog::cancel!("deploy aborted by user");
+/// ```
+#[macro_export]
+macro_rules! cancel {
+    (name: $name:expr, $($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__cancel_named__(&$name, format_args!($($tokens)*)), ())
+    };
+    ($($tokens:tt)*) => {
+        $crate::__if_enabled__!($crate::__cancel_task__(format_args!($($tokens)*)), ())
+    };
+}
 
-        if last_row > 1 {
-            print!("\x1b[{}A\x1b[{}G┣", last_row - 1, (tasks.len() - 1) * 5 + 3)
-        }
+/// Declares the phases of the most recently created task as `(name, weight)` pairs.
+///
+/// The subtasks started after this are its phases, in order. The task
+/// shows which phase is running and how far along it is overall, with
+/// each phase counting by its weight:
+///
+/// ```
+/// lytelog::start!("installing");
+/// lytelog::phases!([("fetch", 0.2), ("build", 0.7), ("package", 0.1)]);
+///
+/// lytelog::start!("fetching sources");
+/// lytelog::pass!("fetched sources");
+///
+/// lytelog::start!("building");
+/// ```
+#[macro_export]
+macro_rules! phases {
+    ($phases:expr) => {
+        $crate::__if_enabled__!($crate::__set_phases__($phases), ())
+    };
+}
 
-        for _ in 1..last_row {
-            print!("\x1b[1D\x1b[1B┃")
-        }
+/// Runs a block as a task whose outcome is the worst outcome of its subtasks.
+///
+/// The group passes if every subtask passed or was skipped, warns if any
+/// of them warned, and fails if any of them failed, keeping its message:
+///
+/// ```no_run
+/// lytelog::group!("deploying {}", "staging" => {
+///     lytelog::start!("api");
+///     lytelog::pass!("api deployed");
+///     lytelog::start!("worker");
+///     lytelog::warn!("worker deployed with a stale config");
+/// });
+/// ```
+///
+/// The group also ends if the block returns early, e.g. with `?`.
+#[macro_export]
+macro_rules! group {
+    ($($tokens:expr),+ => $body:block) => {{
+        let _group = $crate::__group__($crate::__if_enabled__!(
+            $crate::__start_task__(format_args!($($tokens),+), $crate::TaskOptions { group: true, ..Default::default() }),
+            $crate::__untracked__()
+        ));
+        $body
+    }};
+}
 
-        print!("\x1b[u");
-    }
+#[doc(hidden)]
+pub fn __group__(task: TaskHandle) -> Group {
+    Group::new(task)
+}
 
-    tasks.push(Task { row_offset: 0 });
+/// Runs a command as a task, showing the last lines of its output beneath it while it runs.
+///
+/// The task passes if the command exits successfully, and its output is
+/// then cleared away. Otherwise it fails and the last lines of output are
+/// left beneath it. Returns the exit status of the command, or the error
+/// if it could not be run:
+///
+/// ```no_run
+/// use std::process::Command;
+///
+/// let status = lytelog::spawn!("running tests", Command::new("cargo").arg("test"))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// The output is read through pipes, so the command does not see a terminal.
+/// The number of lines shown may be given with `spawn!(tail = 10, "message", command)`.
+#[macro_export]
+macro_rules! spawn {
+    (tail = $tail:expr, $message:expr, $command:expr $(,)?) => {
+        $crate::__spawn__(::std::string::ToString::to_string(&$message), $command, Some($tail))
+    };
+    ($message:expr, $command:expr $(,)?) => {
+        $crate::__spawn__(::std::string::ToString::to_string(&$message), $command, None)
+    };
+}
 
-    if tasks.len() > 1 {
-        print!("{}", " ".repeat((tasks.len() - 2) * 5 + 2) + "┗━ ");
-    }
+#[doc(hidden)]
+pub fn __spawn__(message: String, command: impl BorrowMut<Command>, tail: Option<usize>) -> io::Result<ExitStatus> {
+    Logger::current().spawn_with_tail(message, command, tail.unwrap_or(process::TAIL_LINES))
+}
 
-    // attempts to print message, ignore if flush fails
-    print!("\x1b[33;1m-\x1b[0m {message}");
-    _ = io::stdout().flush();
+/// Runs a closure as a task, calling it again while it fails, up to a number of attempts.
+///
+/// While it retries, the task shows which attempt it is on, e.g. `connecting (attempt 2/5)`.
+/// The task passes if the first attempt succeeds, warns if a later one does, and fails with
+/// the last error once every attempt has failed. Returns what the last attempt returned:
+///
+/// ```no_run
+/// # fn connect() -> std::io::Result<()> { Ok(()) }
+/// let connection = lytelog::retry!("connecting", 5, || connect())?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[macro_export]
+macro_rules! retry {
+    ($message:expr, $attempts:expr, $f:expr $(,)?) => {
+        $crate::__retry__(::std::string::ToString::to_string(&$message), $attempts, $f)
+    };
+}
 
-    // atomically check if the spinner is running
-    // if not then start the spinner.
-    if SPINNING.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed) == Ok(false) {
-        thread::spawn(spin);
-    }
+/// Runs a closure a number of times as a task, timing every run.
+///
+/// While it runs, the task counts the runs and shows the fastest, average and
+/// slowest so far, e.g. `hashing min 1.21ms avg 1.40ms max 1.92ms (3/10)`, and
+/// it passes with the same numbers once every run is done. Returns them as a `Measurement`:
+///
+/// ```
+/// let measurement = lytelog::measure!("hashing", iterations = 10, || (0..1000u64).sum::<u64>());
+///
+/// assert_eq!(measurement.iterations, 10);
+/// assert!(measurement.min <= measurement.max);
+/// ```
+#[macro_export]
+macro_rules! measure {
+    ($message:expr, iterations = $iterations:expr, $f:expr $(,)?) => {
+        $crate::__measure__(::std::string::ToString::to_string(&$message), $iterations, $f)
+    };
 }
 
 #[doc(hidden)]
-pub fn __end_task__(symbol: &str, message: String) {
-    let mut tasks = TASKS.lock().unwrap();
+pub fn __measure__<T>(message: String, iterations: u32, f: impl FnMut() -> T) -> Measurement {
+    Logger::current().measure(message, iterations, f)
+}
 
-    if let Some(Task {row_offset: row}) = tasks.pop() {
-        let column = tasks.len() * 5 + 1;
+#[doc(hidden)]
+pub fn __retry__<T, E: fmt::Display>(message: String, attempts: u32, f: impl FnMut() -> Result<T, E>) -> Result<T, E> {
+    Logger::current().retry(message, attempts, f)
+}
 
-        // replace spinner with symbol:
-        // \x1b[s           : save cursor's current position
-        // \x1b[{row}A      : move the cursor up to correct row
-        // \x1b[{column}G   : move the cursor to correct column
-        // {symbol}         : print the symbol replacing the spinner
-        // \x1b[K           : clear the current line
-        // {message}        : print the ending message overwriting the old message.
+// the messages of the macros are only formatted if lytelog is enabled,
+// so disabled instrumentation costs no more than checking a flag. with
+// the `disabled` feature, the macros expand to a branch that is never
+// taken, so their arguments are still checked but nothing is run.
 
-        print!("\x1b[s");
+#[cfg(not(feature = "disabled"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_enabled__ {
+    ($enabled:expr, $disabled:expr) => {
+        if $crate::__is_enabled__() {
+            $enabled
+        } else {
+            $disabled
+        }
+    };
+}
 
-        if row > 0 {
-            print!("\x1b[{row}A");
+#[cfg(feature = "disabled")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __if_enabled__ {
+    ($enabled:expr, $disabled:expr) => {
+        if false {
+            $enabled
+        } else {
+            $disabled
         }
+    };
+}
 
-        print!("\x1b[{column}G{symbol} \x1b[K{message}");
+#[doc(hidden)]
+pub fn __is_enabled__() -> bool {
+    is_enabled()
+}
 
-        // restore the cursor's position if not the last task
-        if row != 0 {
-            print!("\x1b[u")
-        }
+#[doc(hidden)]
+pub fn __untracked__() -> TaskHandle {
+    Logger::global().untracked()
+}
 
-        if tasks.len() == 0 {
-            println!();
-        }
+#[doc(hidden)]
+pub fn __start_task__(message: fmt::Arguments<'_>, options: TaskOptions) -> TaskHandle {
+    if !is_enabled() {
+        return __untracked__();
+    }
 
-        _ = io::stdout().flush();
-    } else {
-        // if not task is running, just print the symbol and message
-        println!("{symbol} {message}");
+    Logger::current().start_task(message.to_string(), options)
+}
+
+#[doc(hidden)]
+pub fn __update_task__(id: Option<u64>, message: fmt::Arguments<'_>) {
+    if is_enabled() {
+        Logger::current().update_task(id, message.to_string())
     }
+}
 
+#[doc(hidden)]
+pub fn __set_phases__<S: Into<String>>(phases: impl IntoIterator<Item = (S, f64)>) {
+    if is_enabled() {
+        Logger::current().set_phases(phases)
+    }
 }
 
-fn spin() {
-    let mut spinner = '-';
+#[doc(hidden)]
+pub fn __log__(message: fmt::Arguments<'_>) {
+    if is_enabled() {
+        Logger::current().log_line(message.to_string())
+    }
+}
 
-    loop {
-        let tasks = TASKS.lock().unwrap();
+#[doc(hidden)]
+pub fn __detail__(text: fmt::Arguments<'_>) {
+    if is_enabled() {
+        Logger::current().detail_task(None, text.to_string())
+    }
+}
 
-        // kill the thread if there are no more tasks
-        if tasks.len() == 0 {
-            break;
-        }
+#[doc(hidden)]
+pub fn __out__(text: fmt::Arguments<'_>) {
+    Logger::current().out(text.to_string())
+}
 
-        let mut column = 1;
+#[doc(hidden)]
+pub fn __eout__(text: fmt::Arguments<'_>) {
+    Logger::current().eout(text.to_string())
+}
 
-        for Task { row_offset: row} in tasks.iter() {
-            // replace the spinner with a new spinner:
-            // \x1b[s               : save the cursor's current position
-            // \x1b[{row}A          : move the cursor up to correct row
-            // \x1b[{column}G       : move the cursor to the correct column
-            // \x1b[33;1m           : set the foreground color to yellow and font to bold
-            // {spinner}            : print the updated spinner character
-            // \x1b[0m              : reset all formatting
-            // \x1b[u               : restore saved cursor position
+#[doc(hidden)]
+pub fn __fail_with__(error: &(impl Error + ?Sized), message: Option<fmt::Arguments<'_>>) {
+    if is_enabled() {
+        Logger::current().fail_task(None, error, message.map(|message| message.to_string()))
+    }
+}
 
-            print!("\x1b[s");
+#[doc(hidden)]
+pub fn __end_task__(symbol: impl Into<Symbol>, message: fmt::Arguments<'_>) {
+    if is_enabled() {
+        Logger::current().end_task(None, symbol.into(), message.to_string())
+    }
+}
 
-            if *row > 0 {
-                print!("\x1b[{row}A ")
-            }
+#[doc(hidden)]
+pub fn __cancel_task__(message: fmt::Arguments<'_>) {
+    if is_enabled() {
+        Logger::current().cancel_task(None, message.to_string())
+    }
+}
 
-            print!("\x1b[{column}G\x1b[33;1m{spinner}\x1b[0m\x1b[u");
+#[doc(hidden)]
+pub fn __cancel_named__(name: &str, message: fmt::Arguments<'_>) {
+    if !is_enabled() {
+        return;
+    }
 
-            column += 5;
-        }
+    if let Some(task) = named(name) {
+        task.cancel(message.to_string())
+    }
+}
 
-        // most systems flush stdout by newlines
-        // since no newlines were printed, we need
-        // to flush stdout explicitly
-        _ = io::stdout().flush();
+#[doc(hidden)]
+pub fn __end_named__(name: &str, symbol: impl Into<Symbol>, message: fmt::Arguments<'_>) {
+    if !is_enabled() {
+        return;
+    }
 
-        // update spinner to next spinner character (clockwise)
-        spinner = match spinner {
-            '-' => '\\',
-            '\\' => '|',
-            '|' => '/',
-            '/' => '-',
-            _ => '-', // This is not possible, but Rust demands it.
-        };
+    if let Some(task) = named(name) {
+        task.end(symbol, message.to_string())
+    }
+}
 
-        // drop tasks before the wait so other threads may use it.
-        drop(tasks);
+#[doc(hidden)]
+pub fn __update_named__(name: &str, message: fmt::Arguments<'_>) {
+    if !is_enabled() {
+        return;
+    }
 
-        // wait for 80ms; this can be changed to make the spinner go faster
-        thread::sleep(Duration::from_millis(80));
+    if let Some(task) = named(name) {
+        task.update(message.to_string())
     }
+}
 
-    // if the loop has ended, then the spinner has stopped and
-    // will need to be restarted if another task starts
-    SPINNING.store(false, Ordering::Relaxed);
-}
\ No newline at end of file
+/// Returns the handle of the running task with a name given to `start!`,
+/// or of the one that started last if several tasks have the name.
+///
+/// This ends a task from a callback or a distant module without
+/// passing its handle around, like the `name:` form of `pass!` does:
+///
+/// ```
+/// lytelog::start!(name: "db-migrate", "running migrations");
+///
+/// if let Some(migrations) = lytelog::named("db-migrate") {
+///     migrations.pass("12 migrations applied");
+/// }
+///
+/// assert!(lytelog::named("db-migrate").is_none());
+/// ```
+pub fn named(name: &str) -> Option<TaskHandle> {
+    Logger::current().named(name)
+}
+
+/// Temporarily removes the running tasks from the terminal while `f` runs.
+///
+/// The spinners are paused and the block of task output is erased, so the
+/// closure has a clean terminal to prompt the user or print freely. Once it
+/// returns, the block is redrawn beneath whatever the closure printed and
+/// the spinners resume. Tasks may still be started and ended while suspended.
+pub fn suspend<T>(f: impl FnOnce() -> T) -> T {
+    Logger::current().suspend(f)
+}
+
+/// Returns every running task, with each task followed by its subtasks.
+///
+/// This is a snapshot, so it does not change as tasks start and end,
+/// which makes it useful for watchdogs and for asserting on what is
+/// running in tests:
+///
+/// ```
+/// let _task = lytelog::start!("building");
+/// let tasks = lytelog::tasks();
+///
+/// # #[cfg(not(feature = "disabled"))] {
+/// assert_eq!(tasks[0].message, "building");
+/// assert_eq!(tasks[0].state, lytelog::TaskState::Running);
+/// # }
+/// ```
+pub fn tasks() -> Vec<TaskInfo> {
+    Logger::current().tasks()
+}
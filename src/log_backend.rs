@@ -0,0 +1,44 @@
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::{__log__, style::paint, Color};
+
+/// Routes records from the `log` crate through `log!`.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let color = match record.level() {
+            Level::Error => Color::Red,
+            Level::Warn => Color::Yellow,
+            Level::Info => Color::Green,
+            Level::Debug => Color::Blue,
+            Level::Trace => Color::Gray,
+        };
+
+        let level = record.level().as_str().to_ascii_lowercase();
+
+        __log__(format_args!("{}: {}", paint(color, &level), record.args()));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs lytelog as the logger for the `log` crate.
+///
+/// Records at or below `level` are printed like `log!`, beneath
+/// the running task if there is one, so they do not break the
+/// spinners. Fails if another logger was already installed.
+pub fn init_log(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&Logger)?;
+    log::set_max_level(level);
+
+    Ok(())
+}
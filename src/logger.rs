@@ -0,0 +1,1188 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    io::Write,
+    mem,
+    ops::{Deref, DerefMut},
+    sync::{atomic::{AtomicU64, Ordering}, mpsc::{self, Sender}, Arc, LazyLock, Mutex, MutexGuard, OnceLock, Weak},
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use crate::{
+    block::{Row, RowKind},
+    event::{dispatch, TaskEvent},
+    format,
+    filter::{is_enabled, is_quiet, is_visible},
+    output::is_structured,
+    process::TAIL_LINES,
+    render::{self, Draw, Writer},
+    terminal::Stream,
+    spinner::spinner_style,
+    task::{debounce, run_started, timestamp_prefix, Group, Progress, ProgressStyle, Rollup, Task},
+    theme::theme,
+    time::Instant,
+    Outcome, RunningState, SpinnerStyle, Symbol, TaskHandle, TaskInfo, TaskOptions, TerminalBackend,
+};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The logger the macros use on this thread instead of the global one, while capturing.
+    static CURRENT: RefCell<Option<Logger>> = const { RefCell::new(None) };
+}
+
+/// Every logger that has been created, so a panic or signal can end all of their tasks.
+static LOGGERS: Mutex<Vec<Weak<Shared>>> = Mutex::new(Vec::new());
+
+/// A tree of tasks with its own renderer, independent of every other logger.
+///
+/// The macros and free functions of lytelog use `Logger::global()`, which
+/// draws to stdout, unless they are called inside `capture`. Separate loggers are for library code that should not
+/// touch the tasks of the application using it, or for drawing a second tree
+/// to another writer. Settings like the theme, JSON output and the tee file
+/// are shared by every logger.
+#[derive(Clone)]
+pub struct Logger {
+    shared: Arc<Shared>,
+}
+
+/// What a logger shares with its handles and its renderer.
+pub(crate) struct Shared {
+    pub state: Mutex<State>,
+    /// where the renderer draws to, until it is started
+    writer: Mutex<Option<Writer>>,
+    /// the spinner style of tasks started on this logger, if it has one
+    spinner: Mutex<Option<SpinnerStyle>>,
+    renderer: OnceLock<Sender<Draw>>,
+    /// held by the renderer while it draws, which the state is never locked for
+    pub drawing: Mutex<()>,
+}
+
+impl Shared {
+    /// Returns what a logger with no tasks shares, drawing to `writer` once it starts drawing.
+    pub(crate) fn new(writer: Writer) -> Self {
+        Self {
+            state: Mutex::new(State {
+                tasks: Vec::new(),
+                contexts: Vec::new(),
+                suspended: false,
+                revision: 0,
+                events: Vec::new(),
+            }),
+            writer: Mutex::new(Some(writer)),
+            spinner: Mutex::new(None),
+            renderer: OnceLock::new(),
+            drawing: Mutex::new(()),
+        }
+    }
+}
+
+/// Everything a logger keeps track of while tasks are running.
+///
+/// Nothing is drawn while this is locked, drawing is left to the renderer
+/// thread in `render`, which draws from a copy of the tasks. The state is
+/// only locked by the renderer for as long as it takes to copy them, so
+/// starting and ending tasks never waits for a frame to be drawn.
+pub(crate) struct State {
+    /// the running tasks, in the order they were started. the last
+    /// one outside of any context is the innermost task that the
+    /// macros act on, for threads that are not in a context.
+    pub tasks: Vec<Task>,
+    /// the contexts threads have entered, in the order they were entered
+    contexts: Vec<Context>,
+    /// set while `suspend` has the terminal
+    suspended: bool,
+    /// counts how many times the state was locked, so the renderer
+    /// only copies the tasks again once they may have changed
+    pub revision: u64,
+    /// what happened to tasks while the state is locked, dispatched once it is unlocked
+    events: Vec<TaskEvent>,
+}
+
+/// The state of a logger while it is locked.
+///
+/// The events that happened while it was locked are dispatched once it
+/// is unlocked, so the hooks they are passed to can use lytelog themselves.
+struct Locked<'a> {
+    guard: Option<MutexGuard<'a, State>>,
+}
+
+impl Deref for Locked<'_> {
+    type Target = State;
+
+    fn deref(&self) -> &State {
+        self.guard.as_ref().expect("the state is only unlocked when it is dropped")
+    }
+}
+
+impl DerefMut for Locked<'_> {
+    fn deref_mut(&mut self) -> &mut State {
+        self.guard.as_mut().expect("the state is only unlocked when it is dropped")
+    }
+}
+
+impl Drop for Locked<'_> {
+    fn drop(&mut self) {
+        let Some(mut guard) = self.guard.take() else { return };
+        let events = mem::take(&mut guard.events);
+        drop(guard);
+
+        for event in events {
+            dispatch(event);
+        }
+    }
+}
+
+/// A subtree of tasks that the macros on one thread act on,
+/// entered with `TaskHandle::enter`.
+struct Context {
+    id: u64,
+    thread: ThreadId,
+    /// the task that tasks started in the context are subtasks of
+    root: u64,
+}
+
+impl State {
+    fn task(&self, id: u64) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    /// Returns the context the current thread is in, if any.
+    fn context(&self) -> Option<&Context> {
+        let thread = thread::current().id();
+        self.contexts.iter().rev().find(|context| context.thread == thread)
+    }
+
+    /// Returns the innermost task that the macros act on from the current thread.
+    fn innermost(&self) -> Option<&Task> {
+        let context = self.context().map(|context| context.id);
+        self.tasks.iter().rev().find(|task| task.context == context)
+    }
+
+    /// Records activity on a running task, so it is not stalled.
+    fn touch(&mut self, id: Option<u64>) {
+        if let Some(task) = self.tasks.iter_mut().find(|task| Some(task.id) == id) {
+            task.active = Instant::now();
+        }
+    }
+
+    /// Returns the ids of a running task and its running ancestors, from the task outwards.
+    fn lineage(&self, id: Option<u64>) -> Vec<u64> {
+        let mut lineage = Vec::new();
+        let mut next = id.and_then(|id| self.task(id));
+
+        while let Some(task) = next {
+            lineage.push(task.id);
+            next = task.parent.and_then(|parent| self.task(parent));
+        }
+
+        lineage
+    }
+
+    /// Returns the nearest of a task and its ancestors that is drawn.
+    fn drawn(&self, id: Option<u64>) -> Option<&Task> {
+        self.lineage(id).into_iter().filter_map(|id| self.task(id)).find(|task| !task.hidden)
+    }
+}
+
+impl Logger {
+    /// Creates a logger that draws to stdout.
+    pub fn new() -> Self {
+        Self::with(Writer::Stdout)
+    }
+
+    /// Creates a logger that draws to stderr.
+    pub fn stderr() -> Self {
+        Self::with(Writer::Stderr)
+    }
+
+    /// Creates a logger that draws to any writer.
+    ///
+    /// The writer is not treated as a terminal, so rows are never
+    /// cut to fit and the cursor is left alone.
+    pub fn with_writer(writer: impl Write + Send + 'static) -> Self {
+        Self::with(Writer::Other(Box::new(writer)))
+    }
+
+    /// Creates a logger that draws through a backend, like one that
+    /// draws with crossterm or one that records what is drawn in tests.
+    ///
+    /// Rows are cut to the width the backend returns.
+    pub fn with_backend(backend: impl TerminalBackend + 'static) -> Self {
+        Self::with(Writer::Backend(Box::new(backend)))
+    }
+
+    /// Returns the logger used by the macros, which draws to stdout.
+    pub fn global() -> &'static Logger {
+        static GLOBAL: LazyLock<Logger> = LazyLock::new(Logger::new);
+        &GLOBAL
+    }
+
+    /// Returns the logger the macros use on the current thread.
+    pub(crate) fn current() -> Logger {
+        CURRENT.with_borrow(|current| current.clone()).unwrap_or_else(|| Logger::global().clone())
+    }
+
+    /// Makes the macros on the current thread use a logger, returning the one they used before.
+    pub(crate) fn set_current(logger: Option<Logger>) -> Option<Logger> {
+        CURRENT.replace(logger)
+    }
+
+    /// Creates a logger that keeps what it draws, for `capture`.
+    pub(crate) fn capturing() -> Self {
+        Self::with(Writer::Capture)
+    }
+
+    /// Returns what a logger from `capturing` has drawn so far, as plain text.
+    pub(crate) fn captured(&self) -> String {
+        let (sender, receiver) = mpsc::channel();
+        self.send(Draw::Snapshot(sender));
+        receiver.recv().unwrap_or_default()
+    }
+
+    /// Runs `f` between two frames of the renderer, so nothing is drawn while it runs.
+    pub(crate) fn between_frames(&self, f: impl FnOnce()) {
+        let _drawing = self.shared.drawing.lock().unwrap();
+        f()
+    }
+
+    /// Replaces the writer the renderer will draw to, if it has not started yet.
+    pub(crate) fn set_writer(&self, writer: Writer) {
+        let mut current = self.shared.writer.lock().unwrap();
+
+        // the renderer takes the writer when it starts
+        if current.is_some() {
+            *current = Some(writer);
+        }
+    }
+
+    /// Returns the logger that a renderer draws the tasks of.
+    pub(crate) fn from_shared(shared: Arc<Shared>) -> Self {
+        Self { shared }
+    }
+
+    fn with(writer: Writer) -> Self {
+        let shared = Arc::new(Shared::new(writer));
+
+        let mut loggers = LOGGERS.lock().unwrap();
+        loggers.retain(|logger| logger.strong_count() > 0);
+        loggers.push(Arc::downgrade(&shared));
+
+        Self { shared }
+    }
+
+    /// Starts a task, or a subtask of the innermost running task.
+    pub fn start(&self, message: impl Into<String>) -> TaskHandle {
+        self.start_task(message.into(), TaskOptions::default())
+    }
+
+    /// Starts a task with a progress bar, like `progress!`.
+    pub fn progress(&self, message: impl Into<String>) -> TaskHandle {
+        let options = TaskOptions { progress: Some(ProgressStyle::Bar), length: 100, ..Default::default() };
+        self.start_task(message.into(), options)
+    }
+
+    /// Starts a task that fails once `timeout` has passed, like `start_with_timeout!`.
+    pub fn start_with_timeout(&self, message: impl Into<String>, timeout: Duration) -> TaskHandle {
+        let options = TaskOptions { timeout: Some(timeout), ..Default::default() };
+        self.start_task(message.into(), options)
+    }
+
+    /// Starts a task with a `(n/total)` counter, like `start_counted!`.
+    pub fn start_counted(&self, message: impl Into<String>, total: u64) -> TaskHandle {
+        let options = TaskOptions { progress: Some(ProgressStyle::Counter), length: total, ..Default::default() };
+        self.start_task(message.into(), options)
+    }
+
+    /// Starts a task that transfers `total` bytes, like `transfer!`.
+    pub fn transfer(&self, message: impl Into<String>, total: u64) -> TaskHandle {
+        let options = TaskOptions { progress: Some(ProgressStyle::Bytes), length: total, ..Default::default() };
+        self.start_task(message.into(), options)
+    }
+
+    /// Replaces the message of the innermost running task.
+    pub fn update(&self, message: impl Into<String>) {
+        self.update_task(None, message.into())
+    }
+
+    /// Prints a line without disturbing running tasks, like `log!`.
+    pub fn log(&self, message: impl Into<String>) {
+        self.log_line(message.into())
+    }
+
+    /// Prints a line to stdout above the running tasks, like `out!`.
+    pub fn out(&self, text: impl Into<String>) {
+        self.output(text.into(), Stream::Stdout)
+    }
+
+    /// Prints a line to stderr above the running tasks, like `eout!`.
+    pub fn eout(&self, text: impl Into<String>) {
+        self.output(text.into(), Stream::Stderr)
+    }
+
+    /// Passes the innermost running task.
+    pub fn pass(&self, message: impl Into<String>) {
+        self.end(Outcome::Pass, message)
+    }
+
+    /// Ends the innermost running task with a warning.
+    pub fn warn(&self, message: impl Into<String>) {
+        self.end(Outcome::Warn, message)
+    }
+
+    /// Fails the innermost running task.
+    pub fn fail(&self, message: impl Into<String>) {
+        self.end(Outcome::Fail, message)
+    }
+
+    /// Skips the innermost running task.
+    pub fn skip(&self, message: impl Into<String>) {
+        self.end(Outcome::Skip, message)
+    }
+
+    /// Ends the innermost running task with any `Symbol`.
+    pub fn end(&self, symbol: impl Into<Symbol>, message: impl Into<String>) {
+        self.end_task(None, symbol.into(), message.into())
+    }
+
+    /// Fails the innermost running task with an error, printing its
+    /// causes beneath it, like `fail!(error = err, "message")`.
+    pub fn fail_with(&self, error: &(impl Error + ?Sized), message: impl Into<String>) {
+        self.fail_task(None, error, Some(message.into()))
+    }
+
+    /// Runs a block as a task whose outcome is the worst outcome of its subtasks, like `group!`.
+    ///
+    /// Subtasks are started on this logger with the methods of the logger.
+    pub fn group<T>(&self, message: impl Into<String>, f: impl FnOnce() -> T) -> T {
+        let options = TaskOptions { group: true, ..Default::default() };
+        let _group = Group::new(self.start_task(message.into(), options));
+
+        f()
+    }
+
+    /// Declares the phases of the innermost running task as `(name, weight)` pairs, like `phases!`.
+    ///
+    /// The subtasks started after this are the phases, in the order they
+    /// are declared, and each counts towards the progress of the task by
+    /// its weight. The task shows which phase is running along with a bar
+    /// for how far along all of its phases are, including the ones that have
+    /// not started yet, so steps that take very different amounts of time
+    /// still add up to a steady percentage.
+    pub fn set_phases<S: Into<String>>(&self, phases: impl IntoIterator<Item = (S, f64)>) {
+        self.phase_task(None, phases.into_iter().map(|(name, weight)| (name.into(), weight)).collect())
+    }
+
+    /// Sets the spinner style of tasks started on this logger after this call,
+    /// in place of the one set with `set_spinner_style`.
+    pub fn set_spinner_style(&self, style: SpinnerStyle) {
+        *self.shared.spinner.lock().unwrap() = Some(style);
+    }
+
+    /// Returns every running task of this logger.
+    ///
+    /// See the free function `tasks`.
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        let state = self.lock();
+        let mut tasks = Vec::with_capacity(state.tasks.len());
+
+        // subtasks are listed right after their parent, in the order they started.
+        // a subtask whose parent has already ended is listed like a task of its own.
+        let is_root = |task: &&Task| task.parent.and_then(|parent| state.task(parent)).is_none();
+        let mut pending: Vec<&Task> = state.tasks.iter().filter(is_root).rev().collect();
+
+        while let Some(task) = pending.pop() {
+            tasks.push(task.info(&state.tasks));
+            pending.extend(state.tasks.iter().filter(|child| child.parent == Some(task.id)).rev());
+        }
+
+        tasks
+    }
+
+    /// Returns the running task of this logger with a name, like `named`.
+    pub fn named(&self, name: &str) -> Option<TaskHandle> {
+        let state = self.lock();
+        let task = state.tasks.iter().rev().find(|task| task.name.as_deref() == Some(name))?;
+
+        Some(TaskHandle { id: task.id, logger: self.clone() })
+    }
+
+    /// Temporarily removes the running tasks of this logger from the terminal while `f` runs.
+    ///
+    /// See the free function `suspend`.
+    pub fn suspend<T>(&self, f: impl FnOnce() -> T) -> T {
+        {
+            let mut state = self.lock();
+
+            // a nested suspend already has a clean terminal
+            if state.suspended {
+                drop(state);
+                return f();
+            }
+
+            state.suspended = true;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.send(Draw::Suspend(sender));
+        _ = receiver.recv();
+
+        let result = f();
+
+        self.lock().suspended = false;
+        self.send(Draw::Resume);
+        self.flush();
+
+        result
+    }
+
+    /// Starts a subtask of the innermost running task, or of the
+    /// root of the current thread's context if it has none.
+    pub(crate) fn start_task(&self, message: String, options: TaskOptions) -> TaskHandle {
+        let state = self.lock();
+
+        let context = state.context().map(|context| (context.id, context.root));
+        let parent = state.innermost().map(|task| task.id).or(context.map(|(_, root)| root));
+
+        self.start_in(state, parent, context.map(|(id, _)| id), message, options)
+    }
+
+    /// Starts a subtask of a running task, or a task of its own if the parent is not running.
+    pub(crate) fn start_under(&self, parent: Option<u64>, message: String, options: TaskOptions) -> TaskHandle {
+        self.start_in(self.lock(), parent, None, message, options)
+    }
+
+    fn start_in(
+        &self,
+        mut state: Locked<'_>,
+        parent: Option<u64>,
+        context: Option<u64>,
+        message: String,
+        options: TaskOptions,
+    ) -> TaskHandle {
+        if !is_enabled() {
+            return self.untracked();
+        }
+
+        // relative timestamps count from when the first task started
+        run_started();
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let spinner = options.spinner.or_else(|| self.shared.spinner.lock().unwrap().clone()).unwrap_or_else(spinner_style);
+        let progress = options.progress.map(|style| Progress::new(style, options.length));
+        let mut parent = parent.and_then(|parent| state.tasks.iter_mut().find(|task| task.id == parent));
+        let depth = parent.as_ref().map_or(0, |parent| parent.depth + 1);
+
+        // subtasks of a task with phases are its phases, in the order they start
+        let phase = parent.as_mut().and_then(|parent| parent.next_phase());
+        let parent = parent.map(|parent| parent.id);
+        let hidden = !is_visible(depth);
+        let handle = TaskHandle { id, logger: self.clone() };
+
+        // structured and quiet output only keep track of tasks, they never draw them
+        let drawn = !is_structured() && !is_quiet() && !hidden;
+        let pending = drawn && !debounce().is_zero();
+
+        state.touch(parent);
+        state.events.push(TaskEvent::Start { id, parent, depth, message: message.clone(), fields: options.fields.clone() });
+
+        state.tasks.push(Task {
+            id,
+            parent,
+            context,
+            depth,
+            message,
+            icon: options.icon,
+            fields: options.fields,
+            name: options.name,
+            started: Instant::now(),
+            active: Instant::now(),
+            deadline: options.timeout.map(|timeout| Instant::now() + timeout),
+            next_frame: Instant::now() + spinner.interval(),
+            spinner,
+            frame: 0,
+            progress,
+            hidden,
+            rollup: Rollup::default(),
+            weight: phase.map_or(1.0, |(_, weight)| weight),
+            stages_done: 0.0,
+            phases: Vec::new(),
+            phases_started: 0,
+            phase: phase.map(|(index, _)| index),
+            running_state: RunningState::Normal,
+            tail: VecDeque::new(),
+            tail_length: TAIL_LINES,
+            group: options.group.then_some(Outcome::Pass),
+            reported: false,
+            pending,
+        });
+
+        if drawn && !pending {
+            self.reveal(&mut state, parent);
+            self.send(Draw::Push { parent, row: Row::new(depth, RowKind::Running(id)) });
+        }
+
+        // the renderer times tasks out and draws debounced tasks, even when it
+        // draws nothing else. the browser has no threads to run it on, so tasks
+        // never time out there, and debounced tasks are only drawn once they end.
+        if (options.timeout.is_some() || pending) && !cfg!(target_arch = "wasm32") {
+            self.send(Draw::Deadline);
+        }
+
+        handle
+    }
+
+    /// Returns a handle to a task that was never started, which does nothing.
+    pub(crate) fn untracked(&self) -> TaskHandle {
+        TaskHandle { id: NEXT_ID.fetch_add(1, Ordering::Relaxed), logger: self.clone() }
+    }
+
+    pub(crate) fn update_task(&self, id: Option<u64>, message: String) {
+        if id.is_none() && !is_enabled() {
+            return;
+        }
+
+        let mut state = self.lock();
+
+        let task = match id {
+            Some(id) => state.tasks.iter_mut().find(|task| task.id == id),
+            None => {
+                let id = state.innermost().map(|task| task.id);
+                state.tasks.iter_mut().find(|task| Some(task.id) == id)
+            }
+        };
+
+        if let Some(task) = task {
+            let (id, depth) = (task.id, task.depth);
+            task.message = message.clone();
+            task.active = Instant::now();
+
+            state.events.push(TaskEvent::Update { id, depth, message });
+
+            if !is_structured() && !is_quiet() {
+                self.send(Draw::Update(id));
+            }
+        }
+    }
+
+    /// Declares the phases of a running task, or of the innermost task if no id is given.
+    pub(crate) fn phase_task(&self, id: Option<u64>, phases: Vec<(String, f64)>) {
+        if id.is_none() && !is_enabled() {
+            return;
+        }
+
+        let mut state = self.lock();
+        let id = id.or_else(|| state.innermost().map(|task| task.id));
+        let Some(task) = state.tasks.iter_mut().find(|task| Some(task.id) == id) else { return };
+
+        task.phases = phases.into_iter().map(|(name, weight)| (name, weight.max(0.0))).collect();
+        task.phases_started = 0;
+
+        if !is_structured() && !is_quiet() {
+            self.send(Draw::Update(task.id));
+        }
+    }
+
+    pub(crate) fn log_line(&self, message: String) {
+        if !is_enabled() {
+            return;
+        }
+
+        let mut state = self.lock();
+
+        // a thread in a context that has no tasks of its own logs under the root of the context
+        let id = state.innermost().map(|task| task.id).or(state.context().map(|context| context.root));
+        let depth = id.and_then(|id| state.task(id)).map_or(0, |task| task.depth + 1);
+
+        state.events.push(TaskEvent::Log { id, depth, message: message.clone() });
+
+        if is_structured() || is_quiet() {
+            return;
+        }
+
+        if state.tasks.is_empty() {
+            self.send(Draw::Print(message));
+
+            // with no tasks running the line is printed before returning,
+            // as if it had been printed directly.
+            drop(state);
+            return self.flush();
+        }
+
+        self.push_lines(&mut state, id, &message);
+    }
+
+    /// Prints output of the application to a stream, erasing the running tasks
+    /// while it is printed and drawing them again beneath it.
+    pub(crate) fn output(&self, text: String, stream: Stream) {
+        // nothing is drawn while disabled or while output is structured, so nothing can be in the way
+        if !is_enabled() || is_structured() {
+            return render::write_to(stream, &text);
+        }
+
+        let state = self.lock();
+
+        // a renderer is only started once there is something to draw, which
+        // leaves no renderer for programs that print without running tasks.
+        // what is captured is kept by the renderer, so it is started to keep it.
+        let capturing = matches!(*self.shared.writer.lock().unwrap(), Some(Writer::Capture));
+
+        if state.tasks.is_empty() && self.shared.renderer.get().is_none() && !capturing {
+            drop(state);
+            return render::write_to(stream, &text);
+        }
+
+        self.send(Draw::Output { text, stream });
+
+        // with no tasks running the line is printed before returning,
+        // as if it had been printed directly.
+        if state.tasks.is_empty() {
+            drop(state);
+            self.flush();
+        }
+    }
+
+    /// Prints lines beneath a running task, or the innermost task if no id is given.
+    pub(crate) fn detail_task(&self, id: Option<u64>, text: String) {
+        if id.is_none() && !is_enabled() {
+            return;
+        }
+
+        let mut state = self.lock();
+
+        // like a logged line, the lines go under the root of the thread's context if it has no tasks of its own
+        let Some(id) = id.or_else(|| state.innermost().map(|task| task.id).or(state.context().map(|context| context.root)))
+        else {
+            // with no task to print beneath, the lines are printed like a logged line
+            drop(state);
+            return self.log_line(text);
+        };
+
+        let Some(depth) = state.task(id).map(|task| task.depth + 1) else { return };
+
+        state.touch(Some(id));
+        state.events.push(TaskEvent::Log { id: Some(id), depth, message: text.clone() });
+
+        if !is_structured() && !is_quiet() {
+            self.push_lines(&mut state, Some(id), &text);
+        }
+    }
+
+    /// Draws lines one level under the nearest drawn of a task and its ancestors.
+    fn push_lines(&self, state: &mut State, id: Option<u64>, text: &str) {
+        let Some(parent) = state.drawn(id) else { return };
+        let (parent, depth) = (Some(parent.id), parent.depth + 1);
+
+        self.reveal(state, parent);
+
+        // a tab would move the cursor by a width the block can not know
+        for line in text.replace('\t', "    ").lines() {
+            self.send(Draw::Push { parent, row: Row::new(depth, RowKind::Text(line.to_string())) });
+        }
+    }
+
+    /// Ends a running task, or the innermost task if no id is given.
+    pub(crate) fn end_task(&self, id: Option<u64>, symbol: Symbol, message: String) {
+        // the innermost task while disabled is one that was never started
+        if id.is_none() && !is_enabled() {
+            return;
+        }
+
+        let mut state = self.lock();
+
+        let position = match id {
+            Some(id) => state.tasks.iter().position(|task| task.id == id),
+            None => {
+                let id = state.innermost().map(|task| task.id);
+                state.tasks.iter().position(|task| Some(task.id) == id)
+            }
+        };
+
+        // a task that has already ended is not ended again
+        if id.is_some() && position.is_none() {
+            return;
+        }
+
+        let task = position.map(|position| state.tasks.remove(position));
+        let parent = task.as_ref().and_then(|task| task.parent);
+        state.touch(parent);
+
+        if let Some(task) = &task {
+            let outcome = task.rollup.outcome().map_or(symbol.outcome(), |rollup| rollup.worst(symbol.outcome()));
+            let stage = (task.phase.is_some() || task.completion(&state.tasks).is_some()).then_some(task.weight);
+            let parent = state.tasks.iter_mut().find(|parent| Some(parent.id) == task.parent);
+
+            if let Some(parent) = parent {
+                // groups end as badly as the worst of their subtasks
+                if let Some(group) = parent.group.as_mut() {
+                    *group = group.worst(outcome);
+                }
+
+                // a stage or phase counts as complete towards its parent's progress once it ends
+                parent.stages_done += stage.unwrap_or(0.0);
+            }
+        }
+
+        let depth = task.as_ref().map_or(0, |task| task.depth);
+
+        state.events.push(TaskEvent::End {
+            id: task.as_ref().map(|task| task.id),
+            depth,
+            symbol: symbol.clone(),
+            message: message.clone(),
+            elapsed: task.as_ref().map(|task| task.started.elapsed()),
+            fields: task.as_ref().map(|task| task.fields.clone()).unwrap_or_default(),
+        });
+
+        if is_structured() {
+            return;
+        }
+
+        if is_quiet() {
+            self.report(&mut state, task, depth, symbol, message);
+        } else if let Some(task) = task {
+            // the live tail only shows what the task is doing while it runs
+            if !task.tail.is_empty() && !task.hidden {
+                self.send(Draw::Collapse { id: task.id, keep: Vec::new() });
+            }
+
+            self.finish(&mut state, task, symbol, message);
+        } else if state.tasks.is_empty() {
+            // if no task is running, just print the symbol and message
+            self.send(Draw::Print(format!("{}{} {message}", timestamp_prefix(), symbol.render())));
+        } else {
+            // tasks of other threads are running, so the line goes beneath them
+            let id = state.context().map(|context| context.root);
+            self.push_lines(&mut state, id, &format!("{}{} {message}", timestamp_prefix(), symbol.render()));
+        }
+
+        // once no tasks are running, everything is printed before
+        // returning, so it can not end up after what is printed next.
+        if state.tasks.is_empty() {
+            drop(state);
+            self.flush();
+        }
+    }
+
+    /// Fails a running task, or the innermost task if no id is given, with an error.
+    ///
+    /// The error is the message if there is no other, and the rest of
+    /// its chain is printed as `caused by:` lines beneath the task.
+    pub(crate) fn fail_task(&self, id: Option<u64>, error: &(impl Error + ?Sized), message: Option<String>) {
+        if id.is_none() && !is_enabled() {
+            return;
+        }
+
+        let mut causes = Vec::new();
+        let mut source = error.source();
+
+        while let Some(error) = source {
+            causes.push(error.to_string());
+            source = error.source();
+        }
+
+        let message = match message {
+            Some(message) => {
+                causes.insert(0, error.to_string());
+                message
+            }
+            None => error.to_string(),
+        };
+
+        let causes: Vec<String> = causes.into_iter().map(|cause| format!("caused by: {cause}")).collect();
+
+        let mut state = self.lock();
+
+        let task = match id {
+            Some(id) => state.task(id),
+            None => state.innermost(),
+        };
+
+        let Some(task) = task else {
+            // with no task to fail the causes are logged after the outcome
+            drop(state);
+            self.end_task(None, Outcome::Fail.into(), message);
+
+            for cause in causes {
+                self.log_line(cause);
+            }
+
+            return;
+        };
+
+        let (id, depth) = (task.id, task.depth + 1);
+
+        for cause in &causes {
+            state.events.push(TaskEvent::Log { id: Some(id), depth, message: cause.clone() });
+        }
+
+        // the causes are drawn while the task is running, so they stay
+        // beneath it, and are printed after its report in quiet mode.
+        if !is_structured() && !is_quiet() {
+            self.push_lines(&mut state, Some(id), &causes.join("\n"));
+        }
+
+        drop(state);
+        self.end_task(Some(id), Outcome::Fail.into(), message);
+
+        if is_quiet() && !is_structured() {
+            self.print_reported(depth, &causes);
+        }
+    }
+
+    /// Adds a line to the live tail of a running task, keeping only the last lines that fit in it.
+    pub(crate) fn tail_line(&self, id: u64, line: String) {
+        let mut state = self.lock();
+        let Some(task) = state.tasks.iter_mut().find(|task| task.id == id) else { return };
+
+        task.tail.push_back(line.clone());
+        task.active = Instant::now();
+
+        let grew = task.tail.len() <= task.tail_length;
+
+        if !grew {
+            task.tail.pop_front();
+        }
+
+        let (depth, index, hidden) = (task.depth + 1, task.tail.len() - 1, task.hidden);
+
+        state.events.push(TaskEvent::Log { id: Some(id), depth, message: line });
+
+        if is_structured() || is_quiet() || hidden {
+            return;
+        }
+
+        // once the tail is full every line moves up one row
+        if grew {
+            self.reveal(&mut state, Some(id));
+            self.send(Draw::Push { parent: Some(id), row: Row::new(depth, RowKind::Tail(id, index)) });
+        } else {
+            self.send(Draw::Update(id));
+        }
+    }
+
+    /// Ends a task with a live tail, leaving the tail beneath it if `keep` is set.
+    pub(crate) fn end_tailed(&self, id: u64, symbol: Symbol, message: String, keep: bool) {
+        let mut state = self.lock();
+        let Some(task) = state.tasks.iter_mut().find(|task| task.id == id) else { return };
+
+        let tail = Vec::from(mem::take(&mut task.tail));
+        let (depth, hidden) = (task.depth + 1, task.hidden);
+        let keep = if keep { tail } else { Vec::new() };
+
+        if !is_structured() && !is_quiet() && !hidden {
+            self.send(Draw::Collapse { id, keep: keep.clone() });
+        }
+
+        drop(state);
+        self.end_task(Some(id), symbol, message);
+
+        if is_quiet() && !is_structured() && !keep.is_empty() {
+            self.print_reported(depth, &keep);
+        }
+    }
+
+    /// Prints lines beneath a task that was just reported in quiet mode.
+    fn print_reported(&self, depth: usize, lines: &[String]) {
+        let indent = " ".repeat(depth * theme().indent);
+
+        for line in lines {
+            self.send(Draw::Print(format!("{indent}{line}")));
+        }
+
+        self.flush();
+    }
+
+    /// Replaces the spinner of a task that ended with its symbol and final message.
+    fn finish(&self, state: &mut State, task: Task, symbol: Symbol, message: String) {
+        if task.hidden {
+            // hidden tasks roll up into their nearest drawn ancestor
+            let ancestor = state.drawn(task.parent).map(|ancestor| ancestor.id);
+
+            if let Some(ancestor) = state.tasks.iter_mut().find(|task| Some(task.id) == ancestor) {
+                ancestor.rollup.add(symbol.outcome());
+            }
+
+            return;
+        }
+
+        // a task ends at least as badly as its worst hidden subtask
+        let symbol = match task.rollup.outcome() {
+            Some(outcome) if outcome.severity() > symbol.outcome().severity() => Symbol::from(outcome),
+            _ => symbol,
+        };
+
+        let text = format!("{}{} {}{message}{}", timestamp_prefix(), symbol.render(), task.icon_prefix(), task.end_suffix());
+
+        // a task that ended before it was drawn only leaves its final line
+        if task.pending {
+            self.reveal(state, task.parent);
+            self.send(Draw::Push { parent: task.parent, row: Row::new(task.depth, RowKind::Finished(text)) });
+        } else {
+            self.send(Draw::End { id: task.id, text });
+        }
+
+        // once the outermost task ends, its block is left
+        // as it is and the next task starts a new one.
+        if state.tasks.is_empty() {
+            self.send(Draw::Close);
+        }
+    }
+
+    /// Prints a task that did not pass in quiet mode, after any of
+    /// its ancestors that have not been printed yet.
+    fn report(&self, state: &mut State, task: Option<Task>, depth: usize, symbol: Symbol, message: String) {
+        if !matches!(symbol.outcome(), Outcome::Warn | Outcome::Fail) {
+            return;
+        }
+
+        let indent = theme().indent;
+        let lineage = state.lineage(task.as_ref().and_then(|task| task.parent));
+
+        for id in lineage.into_iter().rev() {
+            let Some(ancestor) = state.tasks.iter_mut().find(|task| task.id == id) else { continue };
+
+            if !ancestor.reported {
+                ancestor.reported = true;
+                self.send(Draw::Print(format!("{}{}", " ".repeat(ancestor.depth * indent), ancestor.message)));
+            }
+        }
+
+        let icon = task.as_ref().map(|task| task.icon_prefix()).unwrap_or_default();
+        let suffix = task.map(|task| task.end_suffix()).unwrap_or_default();
+
+        let prefix = " ".repeat(depth * indent) + &timestamp_prefix();
+
+        self.send(Draw::Print(format!("{prefix}{} {icon}{message}{suffix}", symbol.render())));
+    }
+
+    /// Makes the macros on the current thread act on subtasks of a task, returning the id of the context.
+    pub(crate) fn enter(&self, root: u64) -> u64 {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        self.reenter(id, root);
+        id
+    }
+
+    /// Enters a context that was entered before, on whichever thread this is.
+    pub(crate) fn reenter(&self, id: u64, root: u64) {
+        self.lock().contexts.push(Context { id, thread: thread::current().id(), root });
+    }
+
+    /// Starts a task for a future that is polled for the first time, returning the id of a context
+    /// of its own, which is entered with `reenter` whenever the future is polled.
+    ///
+    /// The task is a subtask of a future that is being polled on this thread, and of
+    /// nothing otherwise, since whichever task the thread started last may belong to another future.
+    #[cfg(feature = "async")]
+    pub(crate) fn start_polled(&self, message: String) -> (TaskHandle, u64) {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let state = self.lock();
+        let parent = state.context().map(|context| state.innermost().map_or(context.root, |task| task.id));
+
+        (self.start_in(state, parent, Some(id), message, TaskOptions::default()), id)
+    }
+
+    /// Leaves a context entered with `enter`.
+    pub(crate) fn leave(&self, id: u64) {
+        self.lock().contexts.retain(|context| context.id != id);
+    }
+
+    /// Ends a group with the worst outcome of its subtasks, keeping its message.
+    pub(crate) fn end_group(&self, id: u64) {
+        let state = self.lock();
+        let Some(task) = state.task(id) else { return };
+        let (outcome, message) = (task.group.unwrap_or(Outcome::Pass), task.message.clone());
+        drop(state);
+
+        self.end_task(Some(id), outcome.into(), message)
+    }
+
+    /// Fails a task that timed out, along with its running subtasks, innermost first.
+    pub(crate) fn time_out(&self, id: u64) {
+        let state = self.lock();
+        let Some(task) = state.task(id) else { return };
+
+        let timeout = task.deadline.map(|deadline| deadline - task.started).unwrap_or_default();
+        let message = format!("{} (timed out after {})", task.message, format::duration(timeout));
+
+        let subtasks: Vec<(u64, String)> = state
+            .tasks
+            .iter()
+            .rev()
+            .filter(|other| other.id != id && state.lineage(Some(other.id)).contains(&id))
+            .map(|subtask| (subtask.id, subtask.message.clone()))
+            .collect();
+
+        drop(state);
+
+        for (subtask, message) in subtasks {
+            self.end_task(Some(subtask), Outcome::Fail.into(), message);
+        }
+
+        self.end_task(Some(id), Outcome::Fail.into(), message);
+    }
+
+    /// Cancels a running task, or the innermost task if no id is given, ending
+    /// its running subtasks with the cancelled symbol first, innermost first.
+    pub(crate) fn cancel_task(&self, id: Option<u64>, message: String) {
+        if id.is_none() && !is_enabled() {
+            return;
+        }
+
+        let state = self.lock();
+        let Some(id) = id.or_else(|| state.innermost().map(|task| task.id)) else {
+            drop(state);
+            return self.end_task(None, Symbol::cancelled(), message);
+        };
+
+        let subtasks: Vec<(u64, String)> = state
+            .tasks
+            .iter()
+            .rev()
+            .filter(|other| other.id != id && state.lineage(Some(other.id)).contains(&id))
+            .map(|subtask| (subtask.id, subtask.message.clone()))
+            .collect();
+
+        drop(state);
+
+        for (subtask, message) in subtasks {
+            self.end_task(Some(subtask), Symbol::cancelled(), message);
+        }
+
+        self.end_task(Some(id), Symbol::cancelled(), message);
+    }
+
+    /// Draws a debounced task that has run long enough, if it is still running.
+    pub(crate) fn reveal_task(&self, id: u64) {
+        self.reveal(&mut self.lock(), Some(id));
+    }
+
+    /// Draws a task and its ancestors that have not been drawn yet because
+    /// they are debounced, outermost first, so something can go beneath them.
+    fn reveal(&self, state: &mut State, id: Option<u64>) {
+        for id in state.lineage(id).into_iter().rev() {
+            let Some(task) = state.tasks.iter_mut().find(|task| task.id == id && task.pending) else { continue };
+            task.pending = false;
+
+            self.send(Draw::Push { parent: task.parent, row: Row::new(task.depth, RowKind::Running(id)) });
+        }
+    }
+
+    /// Runs a closure on a task if it is still running.
+    pub(crate) fn with_task(&self, id: u64, f: impl FnOnce(&mut Task)) {
+        let mut state = self.lock();
+
+        if let Some(task) = state.tasks.iter_mut().find(|task| task.id == id) {
+            f(task)
+        }
+    }
+
+    fn lock(&self) -> Locked<'_> {
+        // this will never panic since mutex locks can only
+        // fail if the thread holding the lock panics.
+        // this is guarenteed as long as:
+        //      1. the state is never locked outside of lytlog
+        //      2. lytlog code never panics
+        // so long as these two invariants are satisfied
+        // (and they are by design) then locks of the state
+        // will not panic.
+        let mut state = self.shared.state.lock().unwrap();
+
+        // whoever locks the state may change the tasks
+        state.revision += 1;
+        Locked { guard: Some(state) }
+    }
+
+    /// Sends something to be drawn, starting the renderer if it has not been started.
+    fn send(&self, draw: Draw) {
+        let renderer = self.shared.renderer.get_or_init(|| {
+            let writer = self.shared.writer.lock().unwrap().take().unwrap_or(Writer::Stdout);
+            render::spawn(Arc::downgrade(&self.shared), writer)
+        });
+
+        // the renderer only stops if it panicked, and there
+        // is nothing better to do then than to draw nothing.
+        _ = renderer.send(draw);
+    }
+
+    /// Waits until everything sent so far has been drawn.
+    ///
+    /// This must not be called while the state is locked,
+    /// since the renderer needs it to draw the running tasks.
+    fn flush(&self) {
+        // the renderer ends tasks that time out, and can not wait for itself
+        if thread::current().name() == Some(render::THREAD_NAME) {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.send(Draw::Flush(sender));
+        _ = receiver.recv();
+    }
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Logger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Logger").finish_non_exhaustive()
+    }
+}
+
+/// Returns every logger that is still around.
+fn loggers() -> Vec<Logger> {
+    match LOGGERS.lock() {
+        Ok(loggers) => loggers.iter().filter_map(Weak::upgrade).map(|shared| Logger { shared }).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Ends every running task of every logger with a symbol, innermost first, keeping its message.
+pub(crate) fn end_running_tasks(symbol: Symbol) {
+    // the renderer can not wait for itself to draw them
+    if thread::current().name() == Some(render::THREAD_NAME) {
+        return;
+    }
+
+    for logger in loggers() {
+        loop {
+            // a poisoned lock means lytelog itself panicked, so
+            // its state can not be trusted to draw anything.
+            let (id, message) = match logger.shared.state.lock() {
+                Ok(state) => match state.tasks.last() {
+                    Some(task) => (task.id, task.message.clone()),
+                    None => break,
+                },
+                Err(_) => break,
+            };
+
+            logger.end_task(Some(id), symbol.clone(), message);
+        }
+    }
+}
+
+/// Cancels every running task of every logger, then waits for each renderer
+/// that has started to write its final frame and show the cursor.
+pub(crate) fn shut_down() {
+    if thread::current().name() == Some(render::THREAD_NAME) {
+        return;
+    }
+
+    end_running_tasks(Symbol::cancelled());
+
+    for logger in loggers() {
+        // a logger that never drew anything has nothing to write out
+        let Some(renderer) = logger.shared.renderer.get() else { continue };
+
+        let (sender, receiver) = mpsc::channel();
+        _ = renderer.send(Draw::Shutdown(sender));
+        _ = receiver.recv();
+    }
+}
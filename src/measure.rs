@@ -0,0 +1,67 @@
+use std::{hint::black_box, time::Duration};
+
+use crate::{format, time::Instant, Logger};
+
+/// How long the runs of a closure took, as returned by `measure!`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Measurement {
+    pub iterations: u32,
+    /// The fastest run.
+    pub min: Duration,
+    /// The average of every run.
+    pub mean: Duration,
+    /// The slowest run.
+    pub max: Duration,
+    /// How long the runs took together.
+    pub total: Duration,
+}
+
+impl Measurement {
+    fn new(first: Duration) -> Self {
+        Self { iterations: 1, min: first, mean: first, max: first, total: first }
+    }
+
+    fn add(&mut self, run: Duration) {
+        self.iterations += 1;
+        self.min = self.min.min(run);
+        self.max = self.max.max(run);
+        self.total += run;
+        self.mean = self.total / self.iterations;
+    }
+
+    /// Describes the runs, e.g. `min 1.21ms avg 1.40ms max 1.92ms`.
+    fn stats(&self) -> String {
+        format!("min {} avg {} max {}", format::precise(self.min), format::precise(self.mean), format::precise(self.max))
+    }
+}
+
+impl Logger {
+    /// Runs a closure a number of times as a task, timing every run, like `measure!`.
+    pub fn measure<T>(&self, message: impl Into<String>, iterations: u32, mut f: impl FnMut() -> T) -> Measurement {
+        let message = message.into();
+        let iterations = iterations.max(1);
+        let task = self.start_counted(message.clone(), u64::from(iterations));
+
+        // the result is kept from being optimized away along with the work that made it
+        let mut run = || {
+            let started = Instant::now();
+            black_box(f());
+            started.elapsed()
+        };
+
+        let mut measurement = Measurement::new(run());
+        task.tick();
+
+        while measurement.iterations < iterations {
+            task.update(format!("{message} {}", measurement.stats()));
+            measurement.add(run());
+            task.tick();
+        }
+
+        let runs = if iterations == 1 { "run" } else { "runs" };
+        task.pass(format!("{message}: {iterations} {runs}, {}", measurement.stats()));
+
+        measurement
+    }
+}
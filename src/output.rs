@@ -0,0 +1,223 @@
+use std::{
+    fmt::Write,
+    io::{self, Write as _},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::{
+    event::TaskEvent,
+    style::strip_ansi,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How task events are written to stdout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Draw the task tree with spinners and escape codes.
+    #[default]
+    Terminal,
+    /// Write one JSON object per line for every event, such as
+    /// `{"event":"start","id":3,"msg":"building","ts":1700000000000}`.
+    Json,
+    /// Write every event to the browser console, with tasks as
+    /// `console.group`s. This is the default in WebAssembly, where
+    /// there is no terminal to draw on.
+    #[cfg(feature = "wasm")]
+    Console,
+}
+
+static OUTPUT_FORMAT: AtomicU8 = AtomicU8::new(if cfg!(all(feature = "wasm", target_arch = "wasm32")) { 2 } else { 0 });
+
+/// Sets how task events are written. This should be set before the first task starts.
+pub fn set_output_format(format: OutputFormat) {
+    let value = match format {
+        OutputFormat::Terminal => 0,
+        OutputFormat::Json => 1,
+        #[cfg(feature = "wasm")]
+        OutputFormat::Console => 2,
+    };
+
+    OUTPUT_FORMAT.store(value, Ordering::Relaxed);
+}
+
+/// Returns true if events are written as they happen rather than drawn as a tree.
+pub(crate) fn is_structured() -> bool {
+    OUTPUT_FORMAT.load(Ordering::Relaxed) != 0
+}
+
+/// Returns true if events are written as JSON lines.
+pub(crate) fn is_json() -> bool {
+    OUTPUT_FORMAT.load(Ordering::Relaxed) == 1
+}
+
+/// Returns true if events are written to the browser console.
+#[cfg(feature = "wasm")]
+pub(crate) fn is_console() -> bool {
+    OUTPUT_FORMAT.load(Ordering::Relaxed) == 2
+}
+
+/// Prints an event as a JSON line.
+pub(crate) fn write_json(event: &TaskEvent) {
+    match event {
+        TaskEvent::Start { id, parent, depth, message, fields } => {
+            let mut line = JsonLine::new("start").number("id", *id);
+
+            if let Some(parent) = parent {
+                line = line.number("parent", *parent);
+            }
+
+            line.number("depth", *depth as u64).string("msg", message).fields(fields).emit();
+        }
+        TaskEvent::Update { id, message, .. } => {
+            JsonLine::new("update").number("id", *id).string("msg", message).emit();
+        }
+        TaskEvent::Log { id, message, .. } => {
+            let mut line = JsonLine::new("log");
+
+            if let Some(id) = id {
+                line = line.number("id", *id);
+            }
+
+            line.string("msg", message).emit();
+        }
+        TaskEvent::End { id, symbol, message, elapsed, fields, .. } => {
+            let mut line = JsonLine::new(symbol.outcome().name());
+
+            if let Some(glyph) = symbol.custom_glyph() {
+                line = line.string("symbol", glyph);
+            }
+
+            if let Some(id) = id {
+                line = line.number("id", *id);
+            }
+
+            if let Some(elapsed) = elapsed {
+                line = line.number("elapsed_ms", elapsed.as_millis() as u64);
+            }
+
+            line.string("msg", message).fields(fields).emit();
+        }
+    }
+}
+
+/// A single JSON line describing an event.
+struct JsonLine {
+    buffer: String,
+}
+
+impl JsonLine {
+    /// Starts a line for an event, stamped with the current time in
+    /// milliseconds since the Unix epoch.
+    pub fn new(event: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis());
+
+        let mut line = Self { buffer: String::from("{") };
+        line.push_key("event");
+        push_string(&mut line.buffer, event);
+
+        line.number("ts", timestamp as u64)
+    }
+
+    pub fn number(mut self, key: &str, value: u64) -> Self {
+        self.push_key(key);
+        _ = write!(self.buffer, "{value}");
+        self
+    }
+
+    /// Adds a string, without the escape codes of styled messages.
+    pub fn string(mut self, key: &str, value: &str) -> Self {
+        self.push_key(key);
+        push_string(&mut self.buffer, &strip_ansi(value));
+        self
+    }
+
+    /// Adds the fields of a task as an object of strings, if it has any.
+    pub fn fields(mut self, fields: &[(String, String)]) -> Self {
+        if fields.is_empty() {
+            return self;
+        }
+
+        self.push_key("fields");
+        self.buffer.push('{');
+
+        for (index, (key, value)) in fields.iter().enumerate() {
+            if index > 0 {
+                self.buffer.push(',');
+            }
+
+            push_string(&mut self.buffer, key);
+            self.buffer.push(':');
+            push_string(&mut self.buffer, &strip_ansi(value));
+        }
+
+        self.buffer.push('}');
+        self
+    }
+
+    /// Prints the line to stdout.
+    ///
+    /// Errors are ignored, so output piped into a program that stops
+    /// reading, like `head`, can not panic while the state is locked.
+    pub fn emit(mut self) {
+        self.buffer.push('}');
+        _ = writeln!(io::stdout().lock(), "{}", self.buffer);
+    }
+
+    fn push_key(&mut self, key: &str) {
+        if self.buffer.len() > 1 {
+            self.buffer.push(',');
+        }
+
+        push_string(&mut self.buffer, key);
+        self.buffer.push(':');
+    }
+}
+
+/// Appends a quoted and escaped JSON string.
+pub(crate) fn push_string(buffer: &mut String, value: &str) {
+    buffer.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => buffer.push_str("\\\""),
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            c if c.is_control() => _ = write!(buffer, "\\u{:04x}", c as u32),
+            c => buffer.push(c),
+        }
+    }
+
+    buffer.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quoted(value: &str) -> String {
+        let mut buffer = String::new();
+        push_string(&mut buffer, value);
+        buffer
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(quoted("built"), r#""built""#);
+        assert_eq!(quoted(r#"said "hi" in C:\tmp"#), r#""said \"hi\" in C:\\tmp""#);
+        assert_eq!(quoted("one\ntwo\r\tthree"), r#""one\ntwo\r\tthree""#);
+        assert_eq!(quoted("\0\x07\x7f\u{9b}"), r#""\u0000\u0007\u007f\u009b""#);
+        assert_eq!(quoted("✔ ünïcode"), r#""✔ ünïcode""#);
+    }
+
+    #[test]
+    fn messages_are_written_without_escape_codes() {
+        let line = JsonLine::new("log").string("msg", "\x1b[32m\"done\"\x1b[0m\x01").fields(&[("path".into(), "a\\b".into())]);
+
+        assert!(line.buffer.starts_with(r#"{"event":"log","ts":"#));
+        assert!(line.buffer.ends_with(r#","msg":"\"done\"\u0001","fields":{"path":"a\\b"}"#));
+    }
+}
@@ -0,0 +1,22 @@
+use std::panic;
+
+use crate::{event::in_hook, logger::end_running_tasks, Outcome};
+
+/// Installs a panic hook that fails every running task before the panic message is printed.
+///
+/// Without it, a panic leaves the running tasks spinning above the panic message,
+/// or cut off partway through a frame. With it, their spinners are replaced by
+/// failures, the cursor is shown again, and the panic message is printed below
+/// the tree by whichever hook was installed before.
+pub fn install_panic_hook() {
+    let previous = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        // a hook of `on_event` that panics is caught, and the tasks carry on
+        if !in_hook() {
+            end_running_tasks(Outcome::Fail.into());
+        }
+
+        previous(info);
+    }));
+}
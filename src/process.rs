@@ -0,0 +1,96 @@
+use std::{
+    borrow::BorrowMut,
+    io::{self, BufRead, BufReader, Read},
+    process::{Command, ExitStatus, Stdio},
+    thread,
+};
+
+use crate::{filter::is_enabled, style::strip_ansi, Logger, Outcome, TaskOptions};
+
+/// How many lines of output are shown beneath a running child process by default.
+pub(crate) const TAIL_LINES: usize = 5;
+
+impl Logger {
+    /// Runs a command as a task, like `spawn!`.
+    pub fn spawn(&self, message: impl Into<String>, command: impl BorrowMut<Command>) -> io::Result<ExitStatus> {
+        self.spawn_with_tail(message.into(), command, TAIL_LINES)
+    }
+
+    pub(crate) fn spawn_with_tail(
+        &self,
+        message: String,
+        mut command: impl BorrowMut<Command>,
+        length: usize,
+    ) -> io::Result<ExitStatus> {
+        // the command still runs while disabled, it just is not a task
+        if !is_enabled() {
+            return command.borrow_mut().status();
+        }
+
+        let task = self.start_task(message.clone(), TaskOptions::default()).tail(length);
+
+        let spawned = command.borrow_mut().stdout(Stdio::piped()).stderr(Stdio::piped()).spawn();
+
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(error) => {
+                self.fail_task(Some(task.id), &error, Some(message));
+                return Err(error);
+            }
+        };
+
+        // stdout and stderr are read at the same time, so a child
+        // can never block on writing to one while the other is read.
+        let readers: Vec<_> = [child.stdout.take().map(box_reader), child.stderr.take().map(box_reader)]
+            .into_iter()
+            .flatten()
+            .map(|reader| {
+                let (logger, id) = (self.clone(), task.id);
+                thread::spawn(move || read_lines(reader, |line| logger.tail_line(id, line)))
+            })
+            .collect();
+
+        let status = child.wait();
+
+        for reader in readers {
+            _ = reader.join();
+        }
+
+        match &status {
+            // the output of a command that succeeded is not worth keeping
+            Ok(status) if status.success() => self.end_tailed(task.id, Outcome::Pass.into(), message, false),
+            Ok(status) => self.end_tailed(task.id, Outcome::Fail.into(), format!("{message} ({status})"), true),
+            Err(error) => self.fail_task(Some(task.id), error, Some(message)),
+        }
+
+        status
+    }
+}
+
+fn box_reader(reader: impl Read + Send + 'static) -> Box<dyn Read + Send> {
+    Box::new(reader)
+}
+
+/// Calls `f` with every line read until the end, without escape sequences.
+///
+/// Lines that are redrawn in place with `\r`, like progress bars,
+/// only count as what they were last redrawn as.
+fn read_lines(reader: impl Read, mut f: impl FnMut(String)) {
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer);
+                let line = line.trim_end_matches(['\n', '\r']);
+                let line = line.rsplit('\r').next().unwrap_or_default();
+
+                f(strip_ansi(line));
+            }
+        }
+    }
+}
@@ -0,0 +1,913 @@
+use std::{
+    io::{self, Write},
+    mem,
+    sync::{
+        atomic::{AtomicU64, AtomicU8, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, OnceLock, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    backend::{AnsiBackend, TerminalBackend},
+    block::{self, Block, RenderMode, Row, RowKind},
+    environment,
+    logger::Shared,
+    Logger,
+    style::strip_ansi,
+    task::{self, Task},
+    terminal::{self, Stream},
+    time::Instant,
+};
+
+/// The name of the renderer thread.
+pub(crate) const THREAD_NAME: &str = "lytelog";
+
+/// The longest spinners are slowed down to while writing is slow.
+const MAX_THROTTLE: Duration = Duration::from_secs(1);
+
+/// The shortest time between two writes in low-bandwidth mode.
+const LOW_BANDWIDTH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Stands for a frame rate that has not been set.
+const UNSET: u64 = u64::MAX;
+
+/// The shortest time between two frames of a spinner in microseconds, or 0 for no limit.
+static FRAME_INTERVAL: AtomicU64 = AtomicU64::new(UNSET);
+
+/// Sets how many times a second spinners, clocks and progress are redrawn at most.
+///
+/// Spinners normally advance as often as their style says. With a frame
+/// rate, no spinner advances more often than that, which keeps the output
+/// down over slow connections. A rate of 0 removes the limit.
+///
+/// Whatever the frame rate, spinners are slowed down on their own while
+/// the terminal is slow to accept output, like a serial console or a
+/// congested SSH connection, rather than piling up ever more redraws.
+///
+/// Until this is called, the frame rate is read from the `LYTELOG_FPS`
+/// environment variable, so `LYTELOG_FPS=5` redraws five times a second.
+pub fn set_frame_rate(frames_per_second: u32) {
+    FRAME_INTERVAL.store(interval_of(frames_per_second), Ordering::Relaxed);
+}
+
+/// Returns the time between frames in microseconds at a frame rate.
+fn interval_of(frames_per_second: u32) -> u64 {
+    match frames_per_second {
+        0 => 0,
+        frames => 1_000_000 / u64::from(frames),
+    }
+}
+
+static LOW_BANDWIDTH: AtomicU8 = AtomicU8::new(0);
+
+/// Sets whether output is batched for remote sessions with a lot of latency.
+///
+/// In low-bandwidth mode everything that changes is written out together,
+/// at most twice a second, rather than as it happens. A task that is updated
+/// many times in between is only redrawn once, with its latest message,
+/// and spinners turn at most twice a second. Everything is still written
+/// right away once the last task ends, or when `suspend` is called.
+///
+/// Until this is called, low-bandwidth mode follows the `LYTELOG_LOW_BANDWIDTH`
+/// environment variable, so `LYTELOG_LOW_BANDWIDTH=1` turns it on.
+pub fn set_low_bandwidth(enabled: bool) {
+    LOW_BANDWIDTH.store(if enabled { 1 } else { 2 }, Ordering::Relaxed);
+}
+
+/// Returns true if low-bandwidth mode is enabled.
+fn is_low_bandwidth() -> bool {
+    static FROM_ENV: OnceLock<bool> = OnceLock::new();
+
+    match LOW_BANDWIDTH.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => *FROM_ENV.get_or_init(|| environment::flag("LYTELOG_LOW_BANDWIDTH").unwrap_or(false)),
+    }
+}
+
+/// Returns the shortest time between two frames of a spinner.
+fn min_frame_interval() -> Duration {
+    static FROM_ENV: OnceLock<u64> = OnceLock::new();
+
+    let interval = match FRAME_INTERVAL.load(Ordering::Relaxed) {
+        UNSET => *FROM_ENV.get_or_init(|| {
+            let frames = environment::var("LYTELOG_FPS").and_then(|frames| frames.parse().ok());
+            frames.map_or(0, interval_of)
+        }),
+        interval => interval,
+    };
+
+    Duration::from_micros(interval)
+}
+
+/// Something for the renderer thread to draw.
+///
+/// Everything lytelog prints while drawing tasks goes through the
+/// renderer, so escape sequences from different threads can never
+/// be interleaved. Spinners are advanced by the renderer itself
+/// whenever one of them is due for its next frame.
+pub(crate) enum Draw {
+    /// a task started, or a line was logged beneath the running tasks,
+    /// after the rows of the task it belongs under, if any
+    Push { parent: Option<u64>, row: Row },
+    /// the message or the live tail of a running task changed
+    Update(u64),
+    /// a task with a live tail is ending, so its tail is replaced
+    /// with the lines to keep beneath it, if any
+    Collapse { id: u64, keep: Vec<String> },
+    /// a task ended, leaving its final text on its row
+    End { id: u64, text: String },
+    /// the outermost task ended, so its block is left as it is
+    Close,
+    /// a line printed while no tasks are drawn
+    Print(String),
+    /// output of the application for a stream, printed above the running tasks
+    Output { text: String, stream: Stream },
+    /// erases the block until `Resume`, replying once it is gone
+    Suspend(Sender<()>),
+    /// redraws the block after a `Suspend`
+    Resume,
+    /// replies once everything sent before it has been drawn
+    Flush(Sender<()>),
+    /// replies with everything a capturing renderer has drawn, as plain text
+    Snapshot(Sender<String>),
+    /// a task with a timeout or a debounce started, so
+    /// when it times out or is drawn needs to be waited for
+    Deadline,
+    /// writes out everything with the cursor shown, replying once it has
+    Shutdown(Sender<()>),
+}
+
+/// Prints a line to a stream right away.
+pub(crate) fn write_to(stream: Stream, text: &str) {
+    let mut writer: Box<dyn Write> = match stream {
+        Stream::Stdout => Box::new(io::stdout().lock()),
+        Stream::Stderr => Box::new(io::stderr().lock()),
+    };
+
+    _ = writeln!(writer, "{text}");
+    _ = writer.flush();
+}
+
+/// Where a renderer draws to.
+pub(crate) enum Writer {
+    Stdout,
+    Stderr,
+    Other(Box<dyn Write + Send>),
+    Backend(Box<dyn TerminalBackend>),
+    /// nothing is written, what is drawn is kept as text for `capture`
+    Capture,
+}
+
+impl Writer {
+    /// Returns the stream written to, if it may be a terminal.
+    fn stream(&self) -> Option<Stream> {
+        match self {
+            Writer::Stdout => Some(Stream::Stdout),
+            Writer::Stderr => Some(Stream::Stderr),
+            Writer::Other(_) | Writer::Backend(_) | Writer::Capture => None,
+        }
+    }
+
+    /// Returns the backend that draws to the writer.
+    fn into_backend(self) -> Box<dyn TerminalBackend> {
+        let stream = self.stream();
+
+        let writer: Box<dyn Write + Send> = match self {
+            Writer::Stdout => Box::new(io::stdout()),
+            Writer::Stderr => Box::new(io::stderr()),
+            Writer::Other(writer) => writer,
+            Writer::Backend(backend) => return backend,
+            Writer::Capture => Box::new(io::sink()),
+        };
+
+        Box::new(AnsiBackend::new(writer, stream))
+    }
+}
+
+/// Starts a renderer for the tasks of a logger, returning where to send it what to draw.
+///
+/// The renderer stops once the logger and all of its handles are gone.
+pub(crate) fn spawn(shared: Weak<Shared>, writer: Writer) -> Sender<Draw> {
+    let (sender, receiver) = mpsc::channel();
+
+    terminal::watch();
+
+    let renderer = Renderer::new(shared, writer);
+
+    thread::Builder::new()
+        .name(THREAD_NAME.to_string())
+        .spawn(move || renderer.run(receiver))
+        .expect("failed to spawn the lytelog renderer");
+
+    sender
+}
+
+struct Renderer {
+    shared: Weak<Shared>,
+    backend: Box<dyn TerminalBackend>,
+    /// the stream drawn to, if it may be a terminal
+    stream: Option<Stream>,
+    /// the rows drawn since the outermost running task started
+    block: Block,
+    /// set while `suspend` has the terminal, and always while capturing
+    suspended: bool,
+    /// set if what is drawn is kept in `closed` instead of being written
+    capture: bool,
+    /// the text of blocks that were closed while suspended, to print on resume
+    closed: Vec<String>,
+    /// how many times the terminal had been resized when it was last measured
+    resizes: Option<usize>,
+    /// the shortest time between frames that the backend keeps up with
+    throttle: Duration,
+    /// set while the cursor is hidden by a backend that does not draw to a stream
+    cursor_hidden: bool,
+    /// the tasks that were updated since the last write in low-bandwidth mode, to draw just before the next one
+    stale: Vec<u64>,
+    /// when output may be written again in low-bandwidth mode
+    next_write: Instant,
+    /// a copy of the running tasks, which rows are drawn from so the
+    /// state is never locked for longer than it takes to copy it
+    tasks: Vec<Task>,
+    /// the revision of the state the tasks were copied from
+    revision: Option<u64>,
+}
+
+impl Renderer {
+    fn new(shared: Weak<Shared>, writer: Writer) -> Self {
+        let capture = matches!(writer, Writer::Capture);
+
+        Self {
+            shared,
+            stream: writer.stream(),
+            backend: writer.into_backend(),
+            block: Block::default(),
+            // a capturing renderer never draws, it only keeps track of the rows
+            suspended: capture,
+            capture,
+            closed: Vec::new(),
+            resizes: terminal::resizes(),
+            throttle: Duration::ZERO,
+            cursor_hidden: false,
+            stale: Vec::new(),
+            next_write: Instant::now(),
+            tasks: Vec::new(),
+            revision: None,
+        }
+    }
+
+    fn run(mut self, receiver: Receiver<Draw>) {
+        let mut wake: Option<Instant> = None;
+
+        loop {
+            // with no spinner to advance there is nothing to do
+            // until something is sent.
+            let mut draws = match wake {
+                Some(wake) => match receiver.recv_timeout(wake.saturating_duration_since(Instant::now())) {
+                    Ok(draw) => vec![draw],
+                    Err(RecvTimeoutError::Timeout) => Vec::new(),
+                    Err(RecvTimeoutError::Disconnected) => return,
+                },
+                None => match receiver.recv() {
+                    Ok(draw) => vec![draw],
+                    Err(_) => return,
+                },
+            };
+
+            // whatever else was sent in the meantime is drawn along with it
+            draws.extend(receiver.try_iter());
+
+            // the logger is gone once nothing can send to it
+            let Some(shared) = self.shared.upgrade() else { return };
+            let drawing = shared.drawing.lock().unwrap();
+
+            // the tasks are copied after everything to draw was received, so
+            // the copy is at least as recent as the state each draw was sent in
+            self.refresh(&shared);
+
+            for draw in draws {
+                self.apply(draw);
+            }
+
+            // spinners are advanced after every draw, so a
+            // steady stream of updates can not hold them up.
+            wake = self.tick();
+            drop(drawing);
+
+            // what is held back in low-bandwidth mode is written once its time comes
+            for deadline in [self.time_out(&shared), self.reveal(&shared), self.write_batch()].into_iter().flatten() {
+                wake = Some(wake.map_or(deadline, |wake| wake.min(deadline)));
+            }
+        }
+    }
+
+    /// Copies the running tasks of a logger if they changed since they were last
+    /// copied, keeping how far along the spinners of the copied tasks are.
+    fn refresh(&mut self, shared: &Shared) {
+        let state = shared.state.lock().unwrap();
+
+        if self.revision == Some(state.revision) {
+            return;
+        }
+
+        self.revision = Some(state.revision);
+        let copied = mem::replace(&mut self.tasks, state.tasks.clone());
+        drop(state);
+
+        // both copies are in the order the tasks started, which is the order of their ids
+        let mut copied = copied.into_iter().peekable();
+
+        for task in &mut self.tasks {
+            while let Some(old) = copied.next_if(|old| old.id <= task.id) {
+                if old.id == task.id {
+                    task.frame = old.frame;
+                    task.next_frame = old.next_frame;
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, draw: Draw) {
+        match draw {
+            Draw::Push { parent, row } => {
+                let index = self.block.end_of(parent);
+
+                // a capturing renderer keeps the tree, whatever is drawn to the terminal
+                if self.block.rows.is_empty() && !self.capture {
+                    self.block.mode = self.backend.render_mode().unwrap_or_else(|| block::render_mode(self.stream));
+                    self.block.skip_start_lines = !self.backend.prints_started_tasks();
+                }
+
+                if self.suspended {
+                    self.block.rows.insert(index, row);
+                    return;
+                }
+
+                if self.block.rows.is_empty() {
+                    self.block.width = self.backend.width().filter(|width| *width > 0);
+                    self.block.height = self.backend.height();
+
+                    // the cursor would only jump around between rows
+                    self.hide_cursor();
+                }
+
+                self.block.insert(index, row, &self.tasks)
+            }
+            Draw::Update(id) => {
+                // the task may have ended since it was updated
+                let running = self.tasks.iter().any(|task| task.id == id);
+
+                if !running || self.suspended {
+                    return;
+                }
+
+                if is_low_bandwidth() {
+                    if !self.stale.contains(&id) {
+                        self.stale.push(id);
+                    }
+
+                    return;
+                }
+
+                self.draw_task(id);
+            }
+            Draw::Collapse { id, keep } => {
+                let tail = self.block.tail_of(id);
+                let kept = tail.start..tail.end.min(tail.start + keep.len());
+
+                for (index, line) in kept.clone().zip(keep) {
+                    self.block.rows[index].kind = RowKind::Text(line);
+
+                    // kept lines are no longer cut to fit
+                    if !self.suspended {
+                        self.block.draw(index, &self.tasks);
+                    }
+                }
+
+                if self.suspended {
+                    self.block.rows.drain(kept.end..tail.end);
+                } else {
+                    self.block.remove(kept.end..tail.end, &self.tasks);
+                }
+            }
+            Draw::End { id, text } => {
+                if let Some(index) = self.block.position(id) {
+                    self.block.rows[index].kind = RowKind::Finished(text);
+
+                    if !self.suspended {
+                        self.block.draw(index, &self.tasks);
+                    }
+                }
+            }
+            Draw::Close => {
+                if self.suspended {
+                    self.closed.extend(self.block.remaining(&self.tasks));
+                    self.block.rows.clear();
+                } else {
+                    self.block.close(&self.tasks);
+                }
+
+                self.show_cursor();
+            }
+            Draw::Print(line) | Draw::Output { text: line, .. } if self.capture => self.closed.push(line),
+            Draw::Print(line) => {
+                // lines printed between blocks are as plain as the next block would be
+                let line = if block::render_mode(self.stream) == RenderMode::Plain { strip_ansi(&line) } else { line };
+
+                self.block.out.write_styled(&line);
+                self.block.out.write_styled("\n");
+            }
+            Draw::Output { text, stream } => {
+                // the block is erased so the output goes where it was, and drawn again beneath it
+                let drawn = !self.suspended && !self.block.rows.is_empty();
+
+                if drawn {
+                    self.block.clear();
+                }
+
+                if self.stream == Some(stream) {
+                    self.block.out.write_styled(&text);
+                    self.block.out.write_styled("\n");
+                } else {
+                    self.write();
+                    write_to(stream, &text);
+                }
+
+                if drawn {
+                    self.block.draw_all(&self.tasks);
+                }
+            }
+            Draw::Suspend(reply) => {
+                // the block is drawn again from scratch on resume
+                self.stale.clear();
+                self.block.clear();
+                self.suspended = true;
+                self.show_cursor();
+
+                self.write();
+                _ = reply.send(());
+            }
+            Draw::Resume if self.capture => {}
+            Draw::Resume => {
+                self.suspended = false;
+
+                for line in self.closed.drain(..) {
+                    self.block.out.write_styled(&line);
+                    self.block.out.write_styled("\n");
+                }
+
+                self.block.draw_all(&self.tasks);
+
+                if !self.block.rows.is_empty() {
+                    self.hide_cursor();
+                }
+            }
+            Draw::Deadline => {}
+            Draw::Shutdown(reply) => {
+                // a capturing renderer never writes anything, and keeps what it drew for `capture`
+                if !self.capture {
+                    self.shut_down();
+                }
+
+                _ = reply.send(());
+            }
+            Draw::Flush(reply) => {
+                self.draw_stale();
+                self.write();
+                _ = reply.send(());
+            }
+            Draw::Snapshot(reply) => {
+                // running tasks are shown as they are, with their spinners never having turned
+                let rows = (0..self.block.rows.len()).map(|index| self.block.render(index, &self.tasks));
+                let lines: Vec<String> = self.closed.iter().cloned().chain(rows).map(|line| strip_ansi(&line)).collect();
+
+                _ = reply.send(lines.iter().map(|line| line.clone() + "\n").collect());
+            }
+        }
+    }
+
+    /// Leaves the block as it is last drawn and writes it out with the cursor shown.
+    ///
+    /// The renderer keeps running, so tasks started afterwards start a new block.
+    fn shut_down(&mut self) {
+        self.draw_stale();
+
+        if self.suspended {
+            for line in self.closed.drain(..) {
+                self.block.out.write_styled(&line);
+                self.block.out.write_styled("\n");
+            }
+        } else if !self.block.rows.is_empty() {
+            self.block.close(&self.tasks);
+        }
+
+        self.show_cursor();
+        self.write();
+    }
+
+    /// Advances the spinners that are due for their next frame,
+    /// returning when the next one is due.
+    fn tick(&mut self) -> Option<Instant> {
+        if self.suspended || self.block.rows.is_empty() {
+            return None;
+        }
+
+        let now = Instant::now();
+        let interval = self.frame_interval();
+        let mut wake = None;
+        let mut due = Vec::new();
+
+        // only the copy of the tasks is animated, so the state is not locked to turn a spinner
+        for task in self.tasks.iter_mut() {
+            if self.block.position(task.id).is_none() {
+                continue;
+            }
+
+            if task.next_frame <= now {
+                task.frame += 1;
+                task.next_frame = now + task.spinner.interval().max(interval);
+
+                due.push(task.id);
+            }
+
+            // resizes are checked for at least once a second
+            wake = Some(wake.unwrap_or(now + Duration::from_secs(1)).min(task.next_frame));
+        }
+
+        if let Some(width) = self.resized() {
+            self.block.redraw_resized(width, &self.tasks);
+        } else {
+            for id in due {
+                if let Some(index) = self.block.position(id) {
+                    self.block.draw(index, &self.tasks);
+                }
+            }
+        }
+
+        wake
+    }
+
+    /// Fails the tasks that have timed out, returning when the next one times out.
+    fn time_out(&mut self, shared: &Arc<Shared>) -> Option<Instant> {
+        let now = Instant::now();
+
+        // tasks that ended since they were copied are not ended again
+        let deadlines = self.tasks.iter().filter_map(|task| Some((task.id, task.deadline?)));
+
+        let expired: Vec<u64> = deadlines.clone().filter(|(_, deadline)| *deadline <= now).map(|(id, _)| id).collect();
+        let next = deadlines.map(|(_, deadline)| deadline).filter(|deadline| *deadline > now).min();
+
+        // ending them sends more to draw, which is drawn straight after
+        if !expired.is_empty() {
+            let logger = Logger::from_shared(shared.clone());
+
+            for id in expired {
+                logger.time_out(id);
+            }
+        }
+
+        next
+    }
+
+    /// Draws the debounced tasks that have run long enough, returning when the next one has.
+    fn reveal(&mut self, shared: &Arc<Shared>) -> Option<Instant> {
+        let now = Instant::now();
+        let debounce = task::debounce();
+
+        // tasks that were drawn since they were copied are not drawn again
+        let shown = self.tasks.iter().filter(|task| task.pending).map(|task| (task.id, task.started + debounce));
+
+        let due: Vec<u64> = shown.clone().filter(|(_, shown)| *shown <= now).map(|(id, _)| id).collect();
+        let next = shown.map(|(_, shown)| shown).filter(|shown| *shown > now).min();
+
+        // drawing them sends their rows, which are drawn straight after
+        if !due.is_empty() {
+            let logger = Logger::from_shared(shared.clone());
+
+            for id in due {
+                logger.reveal_task(id);
+            }
+        }
+
+        next
+    }
+
+    /// Returns the new width of the terminal if it changed since it was last measured.
+    fn resized(&mut self) -> Option<usize> {
+        let resizes = terminal::resizes();
+
+        if resizes.is_some() && resizes == self.resizes {
+            return None;
+        }
+
+        self.resizes = resizes;
+        self.block.height = self.backend.height();
+
+        let width = self.backend.width().filter(|width| *width > 0)?;
+        (self.block.width != Some(width)).then_some(width)
+    }
+
+    /// Returns the shortest time between two frames of a spinner.
+    fn frame_interval(&self) -> Duration {
+        let interval = min_frame_interval().max(self.throttle);
+
+        if is_low_bandwidth() {
+            interval.max(LOW_BANDWIDTH_INTERVAL)
+        } else {
+            interval
+        }
+    }
+
+    /// Hides the cursor while tasks are drawn, if it is not hidden already.
+    ///
+    /// The cursor of stdout and stderr is shown again if the process exits
+    /// while it is hidden. Other backends only hide it on a terminal.
+    fn hide_cursor(&mut self) {
+        // a block that never moves the cursor has nothing to hide it from
+        if self.block.is_static() {
+            return;
+        }
+
+        let hide = match self.stream {
+            Some(stream) => terminal::hide_cursor(stream),
+            None => self.block.width.is_some() && !mem::replace(&mut self.cursor_hidden, true),
+        };
+
+        if hide {
+            self.block.out.hide_cursor();
+        }
+    }
+
+    fn show_cursor(&mut self) {
+        let show = match self.stream {
+            Some(stream) => terminal::show_cursor(stream),
+            None => mem::take(&mut self.cursor_hidden),
+        };
+
+        if show {
+            self.block.out.show_cursor();
+        }
+    }
+
+    /// Redraws the row of a task and its live tail.
+    fn draw_task(&mut self, id: u64) {
+        if let Some(index) = self.block.position(id) {
+            self.block.draw(index, &self.tasks);
+        }
+
+        for index in self.block.tail_of(id) {
+            self.block.draw(index, &self.tasks);
+        }
+    }
+
+    /// Draws the tasks that were updated since the last write in low-bandwidth mode.
+    fn draw_stale(&mut self) {
+        for id in mem::take(&mut self.stale) {
+            self.draw_task(id);
+        }
+    }
+
+    /// Writes out what was drawn, which low-bandwidth mode holds back until
+    /// a while after the last write, returning when to write it then.
+    fn write_batch(&mut self) -> Option<Instant> {
+        if is_low_bandwidth() {
+            if self.block.out.is_empty() && self.stale.is_empty() {
+                return None;
+            }
+
+            let now = Instant::now();
+
+            if now < self.next_write {
+                return Some(self.next_write);
+            }
+
+            self.draw_stale();
+            self.next_write = now + LOW_BANDWIDTH_INTERVAL;
+        }
+
+        self.write();
+        None
+    }
+
+    /// Writes out everything drawn since the last write.
+    fn write(&mut self) {
+        if self.block.out.is_empty() {
+            return;
+        }
+
+        let started = Instant::now();
+
+        self.block.out.replay(&mut *self.backend);
+        _ = self.backend.flush();
+
+        // writes only take long once the terminal is not keeping up, so
+        // spinners slow down until writing takes at most a quarter of
+        // the time, and speed back up as writes get fast again.
+        let wanted = (started.elapsed() * 4).min(MAX_THROTTLE);
+        self.throttle = wanted.max((self.throttle + wanted) / 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ops::Range,
+        sync::{Mutex, MutexGuard},
+    };
+
+    use super::*;
+
+    /// A backend that records every call made to it, and can be made slow to flush.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        calls: Arc<Mutex<Vec<String>>>,
+        /// how long each flush takes
+        delay: Arc<Mutex<Duration>>,
+    }
+
+    impl Recorder {
+        fn record(&self, call: String) {
+            self.calls.lock().unwrap().push(call);
+        }
+
+        /// Returns the calls made since this was last called.
+        fn take(&self) -> Vec<String> {
+            mem::take(&mut *self.calls.lock().unwrap())
+        }
+
+        fn delay(&self) -> MutexGuard<'_, Duration> {
+            self.delay.lock().unwrap()
+        }
+    }
+
+    impl TerminalBackend for Recorder {
+        fn write_styled(&mut self, text: &str) {
+            self.record(format!("write {text:?}"));
+        }
+
+        fn move_up(&mut self, lines: usize) {
+            self.record(format!("up {lines}"));
+        }
+
+        fn move_down(&mut self, lines: usize) {
+            self.record(format!("down {lines}"));
+        }
+
+        fn move_to(&mut self, column: usize) {
+            self.record(format!("column {column}"));
+        }
+
+        fn move_to_line(&mut self, line: usize) {
+            self.record(format!("line {line}"));
+        }
+
+        fn save_cursor(&mut self) {
+            self.record("save".to_string());
+        }
+
+        fn restore_cursor(&mut self) {
+            self.record("restore".to_string());
+        }
+
+        fn clear_line(&mut self) {
+            self.record("clear line".to_string());
+        }
+
+        fn clear_below(&mut self) {
+            self.record("clear below".to_string());
+        }
+
+        fn set_scroll_region(&mut self, lines: Option<Range<usize>>) {
+            self.record(format!("scroll region {lines:?}"));
+        }
+
+        fn hide_cursor(&mut self) {
+            self.record("hide".to_string());
+        }
+
+        fn show_cursor(&mut self) {
+            self.record("show".to_string());
+        }
+
+        fn width(&self) -> Option<usize> {
+            Some(40)
+        }
+
+        // whatever TERM says where the tests run
+        fn render_mode(&self) -> Option<RenderMode> {
+            Some(RenderMode::Tree)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            thread::sleep(*self.delay());
+            self.record("flush".to_string());
+            Ok(())
+        }
+    }
+
+    fn renderer(recorder: &Recorder) -> Renderer {
+        Renderer::new(Weak::new(), Writer::Backend(Box::new(recorder.clone())))
+    }
+
+    fn push(renderer: &mut Renderer, id: u64) {
+        renderer.apply(Draw::Push { parent: None, row: Row::new(0, RowKind::Running(id)) });
+    }
+
+    fn flush(renderer: &mut Renderer) {
+        let (reply, replied) = mpsc::channel();
+        renderer.apply(Draw::Flush(reply));
+        replied.recv().unwrap();
+    }
+
+    #[test]
+    fn cursor_is_hidden_while_tasks_are_drawn() {
+        let recorder = Recorder::default();
+        let mut renderer = renderer(&recorder);
+
+        push(&mut renderer, 1);
+        renderer.apply(Draw::End { id: 1, text: "built".to_string() });
+        renderer.apply(Draw::Close);
+        flush(&mut renderer);
+
+        assert_eq!(
+            recorder.take(),
+            ["hide", "column 0", "write \"\\u{1b}[0mbuilt\"", "clear line", "write \"\\n\"", "show", "flush"]
+        );
+    }
+
+    #[test]
+    fn cursor_is_shown_while_suspended() {
+        let recorder = Recorder::default();
+        let mut renderer = renderer(&recorder);
+
+        push(&mut renderer, 1);
+        flush(&mut renderer);
+        recorder.take();
+
+        let (reply, replied) = mpsc::channel();
+        renderer.apply(Draw::Suspend(reply));
+        replied.recv().unwrap();
+
+        // the block is erased and the cursor shown before the terminal is handed over
+        assert_eq!(recorder.take(), ["column 0", "clear below", "show", "flush"]);
+
+        renderer.apply(Draw::Resume);
+        flush(&mut renderer);
+
+        assert_eq!(recorder.take(), ["column 0", "clear line", "hide", "flush"]);
+    }
+
+    #[test]
+    fn shutdown_leaves_the_renderer_running() {
+        let recorder = Recorder::default();
+        let mut renderer = renderer(&recorder);
+
+        push(&mut renderer, 1);
+        renderer.apply(Draw::End { id: 1, text: "built".to_string() });
+
+        let (reply, replied) = mpsc::channel();
+        renderer.apply(Draw::Shutdown(reply));
+        replied.recv().unwrap();
+
+        // the block is left behind with the cursor shown, without waiting for a flush
+        let calls = recorder.take();
+        assert_eq!(calls[calls.len() - 3..], ["write \"\\n\"", "show", "flush"]);
+        assert!(renderer.block.rows.is_empty());
+
+        // a task started afterwards starts a new block
+        push(&mut renderer, 2);
+        renderer.apply(Draw::End { id: 2, text: "tested".to_string() });
+        flush(&mut renderer);
+
+        assert_eq!(recorder.take(), ["hide", "column 0", "write \"\\u{1b}[0mtested\"", "clear line", "flush"]);
+    }
+
+    #[test]
+    fn frames_are_throttled_while_writing_is_slow() {
+        let recorder = Recorder::default();
+        let mut renderer = renderer(&recorder);
+
+        *recorder.delay() = Duration::from_millis(20);
+        renderer.block.out.write_styled("slow");
+        renderer.write();
+
+        // writing may take at most a quarter of the time between frames
+        let slow = renderer.throttle;
+        assert!(slow >= Duration::from_millis(80));
+        assert!(renderer.frame_interval() >= slow);
+
+        // and frames speed back up gradually once writing is fast again
+        *recorder.delay() = Duration::ZERO;
+        renderer.block.out.write_styled("fast");
+        renderer.write();
+
+        assert!(renderer.throttle >= slow / 2 && renderer.throttle < slow);
+
+        // nothing is written, or measured, when nothing was drawn
+        renderer.write();
+        assert_eq!(recorder.take(), ["write \"slow\"", "flush", "write \"fast\"", "flush"]);
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt::Display;
+
+use crate::Logger;
+
+impl Logger {
+    /// Runs a closure as a task until it succeeds, like `retry!`.
+    pub fn retry<T, E: Display>(
+        &self,
+        message: impl Into<String>,
+        attempts: u32,
+        mut f: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let message = message.into();
+        let attempts = attempts.max(1);
+        let task = self.start(message.clone());
+        let mut attempt = 1;
+
+        loop {
+            let error = match f() {
+                Ok(value) if attempt == 1 => {
+                    task.pass(message);
+                    return Ok(value);
+                }
+                Ok(value) => {
+                    task.warn(format!("{message} (succeeded on attempt {attempt}/{attempts})"));
+                    return Ok(value);
+                }
+                Err(error) => error,
+            };
+
+            if attempt == attempts {
+                let tries = if attempts == 1 { "attempt" } else { "attempts" };
+                task.fail(format!("{message}: {error} (after {attempts} {tries})"));
+                return Err(error);
+            }
+
+            attempt += 1;
+            task.update(format!("{message} (attempt {attempt}/{attempts})"));
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::logger::shut_down;
+
+/// Ends lytelog's output before the process exits.
+///
+/// Tasks that are still running are cancelled, then every renderer writes
+/// its final frame and shows the cursor again, and this returns once they
+/// have. A short program that returns from `main` right after its last task
+/// could otherwise exit while the final frame is only partly written.
+///
+/// Tasks started after this are drawn as usual, in a block of their own.
+/// `Config::init_guarded` returns a `FlushGuard` that calls this once the
+/// guard is dropped at the end of `main`.
+pub fn shutdown() {
+    shut_down();
+}
+
+/// Calls `shutdown` when it is dropped, returned by `Config::init_guarded`.
+///
+/// Keep it until lytelog is no longer needed, usually the end of `main`:
+///
+/// ```no_run
+/// let _guard = lytelog::Config::builder().init_guarded();
+///
+/// lytelog::start!("building");
+/// lytelog::pass!("built");
+/// ```
+#[must_use = "the final frame is written as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct FlushGuard(());
+
+impl FlushGuard {
+    pub(crate) fn new() -> Self {
+        Self(())
+    }
+}
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        shutdown();
+    }
+}
@@ -0,0 +1,51 @@
+/// Cleans up the terminal when the process is interrupted by `SIGINT` or `SIGTERM`.
+///
+/// The running tasks are ended with the interrupted glyph of the theme,
+/// which counts as a failure, the cursor is shown again, and the signal
+/// is raised again so the process exits with the status expected of it.
+/// Signals are only handled on Unix, elsewhere this does nothing.
+pub fn handle_signals() {
+    #[cfg(unix)]
+    unix::handle_signals();
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{sync::Once, thread};
+
+    use signal_hook::{
+        consts::{SIGINT, SIGTERM},
+        iterator::Signals,
+        low_level::emulate_default_handler,
+    };
+
+    use crate::{logger::end_running_tasks, theme::theme, Outcome, Symbol};
+
+    pub fn handle_signals() {
+        static HANDLED: Once = Once::new();
+
+        HANDLED.call_once(|| {
+            // if the handlers can not be installed the signals
+            // just kill the process, as they always used to.
+            let Ok(mut signals) = Signals::new([SIGINT, SIGTERM]) else {
+                return;
+            };
+
+            thread::spawn(move || {
+                if let Some(signal) = signals.forever().next() {
+                    end_running_tasks(interrupted());
+
+                    // the handlers are left in place, so the default
+                    // action is what would have happened without them.
+                    _ = emulate_default_handler(signal);
+                }
+            });
+        });
+    }
+
+    /// Returns the symbol that interrupted tasks end with.
+    fn interrupted() -> Symbol {
+        let glyph = theme().interrupted;
+        Symbol::custom(glyph.text, glyph.color).with_outcome(Outcome::Fail)
+    }
+}
@@ -0,0 +1,83 @@
+use std::{sync::Mutex, time::Duration};
+
+use crate::{style::visible_width, theme::theme};
+
+/// The frames of a spinner and how long each frame is shown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpinnerStyle {
+    pub frames: Vec<&'static str>,
+    pub interval: Duration,
+}
+
+impl SpinnerStyle {
+    /// Creates a spinner that cycles through `frames`, advancing every `interval`.
+    pub fn new(frames: Vec<&'static str>, interval: Duration) -> Self {
+        Self { frames, interval }
+    }
+
+    /// The classic clockwise line spinner, `- \ | /`, which is also the default.
+    pub fn line() -> Self {
+        Self::default()
+    }
+
+    /// A dot circling inside a braille cell, `⠋ ⠙ ⠹ ⠸`.
+    pub fn dots() -> Self {
+        Self::new(vec!["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"], Duration::from_millis(80))
+    }
+
+    /// A gap circling through a full braille cell, `⣾ ⣽ ⣻ ⢿`.
+    pub fn braille() -> Self {
+        Self::new(vec!["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"], Duration::from_millis(80))
+    }
+
+    /// An arc sweeping around a circle, `◜ ◠ ◝ ◞`.
+    pub fn arc() -> Self {
+        Self::new(vec!["◜", "◠", "◝", "◞", "◡", "◟"], Duration::from_millis(100))
+    }
+
+    /// A clock face whose hand goes around every 1.2 seconds, `🕛 🕐 🕑`.
+    pub fn clock() -> Self {
+        let frames = vec!["🕛", "🕐", "🕑", "🕒", "🕓", "🕔", "🕕", "🕖", "🕗", "🕘", "🕙", "🕚"];
+        Self::new(frames, Duration::from_millis(100))
+    }
+
+    /// Returns the frame shown at a step of the animation.
+    pub(crate) fn frame(&self, step: usize) -> &'static str {
+        match self.frames.len() {
+            0 => "",
+            len => self.frames[step % len],
+        }
+    }
+
+    /// Returns how many columns the widest frame takes up.
+    pub(crate) fn width(&self) -> usize {
+        self.frames.iter().map(|frame| visible_width(frame)).max().unwrap_or(0)
+    }
+
+    /// Returns the interval, never less than a millisecond so
+    /// a zero interval can not busy loop the spinner thread.
+    pub(crate) fn interval(&self) -> Duration {
+        self.interval.max(Duration::from_millis(1))
+    }
+}
+
+impl Default for SpinnerStyle {
+    /// The classic clockwise line spinner at 80ms per frame.
+    fn default() -> Self {
+        Self::new(vec!["-", "\\", "|", "/"], Duration::from_millis(80))
+    }
+}
+
+static SPINNER_STYLE: Mutex<Option<SpinnerStyle>> = Mutex::new(None);
+
+/// Sets the spinner style used by tasks started after this call,
+/// in place of the spinner of the theme.
+pub fn set_spinner_style(style: SpinnerStyle) {
+    *SPINNER_STYLE.lock().unwrap() = Some(style);
+}
+
+/// Returns the current global spinner style.
+pub(crate) fn spinner_style() -> SpinnerStyle {
+    let style = SPINNER_STYLE.lock().unwrap().clone();
+    style.unwrap_or_else(|| theme().spinner.clone())
+}
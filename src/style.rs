@@ -0,0 +1,287 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        OnceLock,
+    },
+};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::environment;
+
+/// A terminal color used when drawing symbols and spinners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Gray,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// From the 256-color palette, which most terminals support.
+    Orange,
+}
+
+impl Color {
+    /// the SGR foreground code for this color
+    fn code(self) -> &'static str {
+        match self {
+            Color::Black => "30",
+            Color::Red => "31",
+            Color::Green => "32",
+            Color::Yellow => "33",
+            Color::Blue => "34",
+            Color::Magenta => "35",
+            Color::Cyan => "36",
+            Color::White => "37",
+            Color::Gray => "90",
+            Color::BrightRed => "91",
+            Color::BrightGreen => "92",
+            Color::BrightYellow => "93",
+            Color::BrightBlue => "94",
+            Color::BrightMagenta => "95",
+            Color::BrightCyan => "96",
+            Color::BrightWhite => "97",
+            Color::Orange => "38;5;208",
+        }
+    }
+}
+
+/// Controls whether lytelog emits color codes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Use color unless the `NO_COLOR` environment variable is set
+    /// or `TERM` is `dumb`.
+    #[default]
+    Auto,
+    /// Always use color.
+    Always,
+    /// Never use color.
+    Never,
+}
+
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(0);
+
+/// Sets whether symbols and spinners are drawn with color.
+pub fn set_color_choice(choice: ColorChoice) {
+    let value = match choice {
+        ColorChoice::Auto => 0,
+        ColorChoice::Always => 1,
+        ColorChoice::Never => 2,
+    };
+
+    COLOR_CHOICE.store(value, Ordering::Relaxed);
+}
+
+/// Returns true if color codes should be emitted.
+pub(crate) fn use_color() -> bool {
+    // https://no-color.org: any non-empty value disables color.
+    // the environment is only read once since it is not
+    // expected to change while tasks are running.
+    static NO_COLOR: OnceLock<bool> = OnceLock::new();
+
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        1 => true,
+        2 => false,
+        _ => !*NO_COLOR.get_or_init(|| environment::var("NO_COLOR").is_some() || environment::is_dumb_terminal()),
+    }
+}
+
+/// Wraps text in bold color codes if color is enabled.
+pub(crate) fn paint(color: Color, text: &str) -> String {
+    if use_color() {
+        format!("\x1b[{};1m{text}\x1b[0m", color.code())
+    } else {
+        text.to_string()
+    }
+}
+
+/// Styles part of a message, like `start!("building {}", style(name).bold().cyan())`.
+///
+/// Without color, like with `NO_COLOR` set, the value is printed as it is.
+pub fn style<T: fmt::Display>(value: T) -> Styled<T> {
+    Styled { value, color: None, bold: false, dim: false, italic: false, underline: false }
+}
+
+/// A value that is displayed with a style, created with `style`.
+#[derive(Clone, Copy, Debug)]
+#[must_use]
+pub struct Styled<T> {
+    value: T,
+    color: Option<Color>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl<T> Styled<T> {
+    pub fn color(self, color: Color) -> Self {
+        Self { color: Some(color), ..self }
+    }
+
+    pub fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+
+    /// Draws the value fainter than the text around it.
+    pub fn dim(self) -> Self {
+        Self { dim: true, ..self }
+    }
+
+    pub fn italic(self) -> Self {
+        Self { italic: true, ..self }
+    }
+
+    pub fn underline(self) -> Self {
+        Self { underline: true, ..self }
+    }
+
+    pub fn red(self) -> Self {
+        self.color(Color::Red)
+    }
+
+    pub fn green(self) -> Self {
+        self.color(Color::Green)
+    }
+
+    pub fn yellow(self) -> Self {
+        self.color(Color::Yellow)
+    }
+
+    pub fn blue(self) -> Self {
+        self.color(Color::Blue)
+    }
+
+    pub fn magenta(self) -> Self {
+        self.color(Color::Magenta)
+    }
+
+    pub fn cyan(self) -> Self {
+        self.color(Color::Cyan)
+    }
+
+    pub fn gray(self) -> Self {
+        self.color(Color::Gray)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Styled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let codes: Vec<String> = [(self.bold, 1), (self.dim, 2), (self.italic, 3), (self.underline, 4)]
+            .into_iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, code)| code.to_string())
+            .chain(self.color.map(|color| color.code().to_string()))
+            .collect();
+
+        if codes.is_empty() || !use_color() {
+            return self.value.fmt(f);
+        }
+
+        write!(f, "\x1b[{}m", codes.join(";"))?;
+        self.value.fmt(f)?;
+        f.write_str("\x1b[0m")
+    }
+}
+
+/// Removes escape sequences from text, leaving what would be visible.
+pub(crate) fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+
+        // a control sequence is `ESC [`, then parameters, then a
+        // final byte from `@` to `~`. anything else after an escape
+        // is a two character sequence.
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+
+    stripped
+}
+
+/// Returns how many columns text takes up in the terminal,
+/// counting wide characters like CJK and emoji as two.
+pub(crate) fn visible_width(text: &str) -> usize {
+    strip_ansi(text).width()
+}
+
+/// Shortens text to at most `width` columns, replacing what was cut
+/// with `…`. Escape sequences before the cut are kept, followed by a
+/// reset so a color that was cut off does not leak into what follows.
+pub(crate) fn truncate(text: &str, width: usize) -> String {
+    if visible_width(text) <= width {
+        return text.to_string();
+    }
+
+    let mut truncated = String::with_capacity(text.len());
+    let mut columns = 0;
+    let mut escaped = false;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            escaped = true;
+            truncated.push(c);
+
+            if let Some(next) = chars.next() {
+                truncated.push(next);
+
+                if next == '[' {
+                    for c in chars.by_ref() {
+                        truncated.push(c);
+
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            continue;
+        }
+
+        let char_width = c.width().unwrap_or(0);
+
+        // leave a column for the ellipsis
+        if columns + char_width + 1 > width {
+            break;
+        }
+
+        truncated.push(c);
+        columns += char_width;
+    }
+
+    if width > 0 {
+        truncated.push('…');
+    }
+
+    if escaped {
+        truncated += "\x1b[0m";
+    }
+
+    truncated
+}
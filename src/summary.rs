@@ -0,0 +1,180 @@
+use std::{process::ExitCode, sync::Mutex, time::Duration};
+
+use crate::{event::TaskEvent, filter::is_enabled, format, time::Instant, Logger, Outcome, Symbol};
+
+/// How many of the slowest tasks `summary` lists.
+const SLOWEST: usize = 5;
+
+static SUMMARY: Mutex<Summary> = Mutex::new(Summary::new());
+
+/// What every task that has ended so far adds up to.
+struct Summary {
+    /// when the first task started
+    started: Option<Instant>,
+    /// how many tasks ended with each outcome, in the order of `OUTCOMES`
+    counts: [u32; 4],
+    /// the slowest tasks so far with how long they ran, slowest first
+    slowest: Vec<(String, Duration)>,
+    /// the worst outcome printed so far, including those printed while no task was running
+    worst: Option<Outcome>,
+}
+
+const OUTCOMES: [(Outcome, &str); 4] = [
+    (Outcome::Pass, "passed"),
+    (Outcome::Warn, "warned"),
+    (Outcome::Fail, "failed"),
+    (Outcome::Skip, "skipped"),
+];
+
+impl Summary {
+    const fn new() -> Self {
+        Self { started: None, counts: [0; 4], slowest: Vec::new(), worst: None }
+    }
+}
+
+/// Prints a digest of every task that has ended so far, for the end of a run.
+///
+/// The footer counts how many tasks passed, warned, failed and were skipped,
+/// lists the slowest of them, and says how long it has been since the first
+/// task started:
+///
+/// ```text
+/// 80 tasks in 3m 12s: 76 passed, 2 warned, 1 failed, 1 skipped
+/// slowest:
+///    42.1s  deploying api
+///    18.0s  migrating database
+/// ```
+pub fn summary() {
+    if !is_enabled() {
+        return;
+    }
+
+    let lines = {
+        let summary = SUMMARY.lock().unwrap();
+
+        let total: u32 = summary.counts.iter().sum();
+        let elapsed = summary.started.map(|started| started.elapsed()).unwrap_or_default();
+
+        let counts: Vec<String> = OUTCOMES
+            .iter()
+            .zip(summary.counts)
+            .filter(|(_, count)| *count > 0)
+            .map(|((outcome, label), count)| format!("{} {count} {label}", Symbol::from(*outcome).render()))
+            .collect();
+
+        let tasks = if total == 1 { "task" } else { "tasks" };
+        let mut lines = vec![format!("{total} {tasks} in {}: {}", format::duration(elapsed), counts.join(", "))];
+
+        if !summary.slowest.is_empty() {
+            lines.push("slowest:".to_string());
+
+            for (message, elapsed) in &summary.slowest {
+                lines.push(format!("  {:>7}  {message}", format::duration(*elapsed)));
+            }
+        }
+
+        lines
+    };
+
+    Logger::current().log(lines.join("\n"));
+}
+
+/// How the tasks of a run ended, as returned by `finish`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RunResult {
+    pub passed: u32,
+    pub warned: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    /// How long it has been since the first task started.
+    pub elapsed: Duration,
+    worst: Option<Outcome>,
+}
+
+impl RunResult {
+    /// Returns the worst outcome of the run, counting outcomes printed
+    /// while no task was running, or `Outcome::Pass` if there were none.
+    pub fn outcome(&self) -> Outcome {
+        self.worst.unwrap_or(Outcome::Pass)
+    }
+
+    /// Returns true unless anything failed.
+    pub fn is_success(&self) -> bool {
+        self.outcome() != Outcome::Fail
+    }
+
+    /// Returns the exit code for the run: 0 if nothing warned or failed,
+    /// 1 if anything failed, and 2 if something warned but nothing failed.
+    pub fn to_exit_code(&self) -> ExitCode {
+        match self.outcome() {
+            Outcome::Pass | Outcome::Skip => ExitCode::SUCCESS,
+            Outcome::Fail => ExitCode::FAILURE,
+            Outcome::Warn => ExitCode::from(2),
+        }
+    }
+}
+
+/// Returns how every task that has ended so far went, for the end of a run.
+///
+/// With it, `main` can exit with a code that matches what was printed:
+///
+/// ```no_run
+/// use std::process::ExitCode;
+///
+/// fn main() -> ExitCode {
+///     lytelog::start!("building");
+///     lytelog::warn!("built with warnings");
+///
+///     lytelog::finish().to_exit_code()
+/// }
+/// ```
+///
+/// Tasks that are still running are not counted.
+pub fn finish() -> RunResult {
+    let summary = SUMMARY.lock().unwrap();
+    let [passed, warned, failed, skipped] = summary.counts;
+
+    RunResult {
+        passed,
+        warned,
+        failed,
+        skipped,
+        elapsed: summary.started.map(|started| started.elapsed()).unwrap_or_default(),
+        worst: summary.worst,
+    }
+}
+
+/// Adds an event to the summary.
+pub(crate) fn record(event: &TaskEvent) {
+    let mut summary = SUMMARY.lock().unwrap();
+
+    if let TaskEvent::End { symbol, .. } = event {
+        let outcome = symbol.outcome();
+        summary.worst = Some(summary.worst.map_or(outcome, |worst| worst.worst(outcome)));
+    }
+
+    match event {
+        TaskEvent::Start { .. } => {
+            summary.started.get_or_insert_with(Instant::now);
+        }
+        // outcomes printed while no task was running are not tasks
+        TaskEvent::End { id: Some(_), symbol, message, elapsed, .. } => {
+            let outcome = symbol.outcome();
+
+            if let Some(index) = OUTCOMES.iter().position(|(other, _)| *other == outcome) {
+                summary.counts[index] += 1;
+            }
+
+            if let Some(elapsed) = *elapsed {
+                let index = summary.slowest.partition_point(|(_, other)| *other >= elapsed);
+
+                if index < SLOWEST {
+                    summary.slowest.insert(index, (message.clone(), elapsed));
+                    summary.slowest.truncate(SLOWEST);
+                }
+            }
+        }
+        _ => {}
+    }
+}
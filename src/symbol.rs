@@ -0,0 +1,64 @@
+use std::borrow::Cow;
+
+use crate::{style::paint, theme::theme, Color, Outcome};
+
+/// What replaces the spinner of a task when it ends.
+///
+/// Every outcome has a built-in symbol that follows the charset, and
+/// `Symbol::custom` makes new ones, e.g. for tasks that were cached or retried.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symbol {
+    outcome: Outcome,
+    custom: Option<(Cow<'static, str>, Color)>,
+}
+
+impl Symbol {
+    /// Creates a symbol with a custom glyph and color.
+    ///
+    /// Tasks ending with it count as passed, which can be changed with `with_outcome`.
+    pub fn custom(glyph: impl Into<Cow<'static, str>>, color: Color) -> Self {
+        Self { outcome: Outcome::Pass, custom: Some((glyph.into(), color)) }
+    }
+
+    /// Sets the outcome that tasks ending with this symbol count as.
+    pub fn with_outcome(mut self, outcome: Outcome) -> Self {
+        self.outcome = outcome;
+        self
+    }
+
+    /// Returns the outcome that tasks ending with this symbol count as.
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Returns the symbol that cancelled tasks end with, which counts as skipped.
+    pub(crate) fn cancelled() -> Self {
+        let glyph = theme().cancelled;
+        Self::custom(glyph.text, glyph.color).with_outcome(Outcome::Skip)
+    }
+
+    /// Returns the glyph of a custom symbol.
+    pub(crate) fn custom_glyph(&self) -> Option<&str> {
+        self.custom.as_ref().map(|(glyph, _)| glyph.as_ref())
+    }
+
+    /// Returns the symbol as it is drawn, with color if enabled.
+    pub(crate) fn render(&self) -> String {
+        let (glyph, color) = match &self.custom {
+            Some((glyph, color)) => (glyph.as_ref(), *color),
+            None => {
+                let glyph = theme().outcome(self.outcome);
+                return paint(glyph.color, glyph.text);
+            }
+        };
+
+        paint(color, glyph)
+    }
+}
+
+impl From<Outcome> for Symbol {
+    fn from(outcome: Outcome) -> Self {
+        Self { outcome, custom: None }
+    }
+}
@@ -0,0 +1,836 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+use crate::{
+    format,
+    style::style,
+    theme::theme,
+    time::{Instant, SystemTime},
+    Color, Logger, Outcome, SpinnerStyle, Symbol,
+};
+
+/// The width in columns of a progress bar.
+const BAR_WIDTH: usize = 20;
+
+/// The shortest time between two throughput samples.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How much weight a new throughput sample is given over the average.
+const SMOOTHING: f64 = 0.3;
+
+static SHOW_ELAPSED: AtomicBool = AtomicBool::new(false);
+static SHOW_CLOCK: AtomicBool = AtomicBool::new(false);
+static STATUS_COLUMN: AtomicBool = AtomicBool::new(false);
+/// how long a task may go without activity before it is stalled, in milliseconds, or 0 for never
+static STALL_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+/// How long a task runs before its spinner is drawn, in milliseconds.
+static DEBOUNCE: AtomicU64 = AtomicU64::new(0);
+
+/// Sets whether completed tasks show how long they ran, e.g. `✔ built crate (3.2s)`.
+pub fn set_show_elapsed(show: bool) {
+    SHOW_ELAPSED.store(show, Ordering::Relaxed);
+}
+
+/// Sets how long a task may go without an update or progress before it is shown as stalled.
+///
+/// A stalled task has its spinner drawn in the color of a failure and
+/// `(stalled 30s)` appended, so hung steps stand out during long runs.
+/// Tasks with running subtasks are waiting on them and never stall.
+/// `None`, the default, turns this off.
+pub fn set_stall_timeout(timeout: Option<Duration>) {
+    let millis = timeout.map_or(0, |timeout| timeout.as_millis().clamp(1, u64::MAX.into()) as u64);
+    STALL_TIMEOUT.store(millis, Ordering::Relaxed);
+}
+
+/// Sets how long a task has to run before its spinner is drawn.
+///
+/// Tasks that end sooner only print the line they end with, so a run of
+/// quick steps does not flash a spinner frame for each of them. A task is
+/// drawn early if a subtask or a line needs to go beneath it. Zero, the
+/// default, draws every task as soon as it starts.
+pub fn set_debounce(threshold: Duration) {
+    DEBOUNCE.store(threshold.as_millis().min(u64::MAX.into()) as u64, Ordering::Relaxed);
+}
+
+/// Returns how long a task has to run before its spinner is drawn.
+pub(crate) fn debounce() -> Duration {
+    Duration::from_millis(DEBOUNCE.load(Ordering::Relaxed))
+}
+
+/// What the line a task ends with is prefixed with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Timestamps {
+    /// Nothing.
+    #[default]
+    Off,
+    /// How long after the first task started the task ended, e.g. `[00:03.214] ✔ built`.
+    Relative,
+    /// The time of day the task ended in UTC, e.g. `[13:37:00.250] ✔ built`,
+    /// which lines up with the timestamps of the tee file and most server logs.
+    WallClock,
+}
+
+static TIMESTAMPS: AtomicU8 = AtomicU8::new(0);
+
+/// Sets what the line each task ends with is prefixed with, so a
+/// transcript of the run can be lined up with logs from elsewhere.
+pub fn set_timestamps(timestamps: Timestamps) {
+    let value = match timestamps {
+        Timestamps::Off => 0,
+        Timestamps::Relative => 1,
+        Timestamps::WallClock => 2,
+    };
+
+    TIMESTAMPS.store(value, Ordering::Relaxed);
+}
+
+/// Returns when the first task started, which relative timestamps count from.
+pub(crate) fn run_started() -> Instant {
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+    *STARTED.get_or_init(Instant::now)
+}
+
+/// Returns the dimmed timestamp and space that the line a task ends with starts with, if any.
+pub(crate) fn timestamp_prefix() -> String {
+    let timestamp = match TIMESTAMPS.load(Ordering::Relaxed) {
+        1 => format::offset(run_started().elapsed()),
+        2 => format::time_of_day(SystemTime::now()),
+        _ => return String::new(),
+    };
+
+    format!("{} ", style(format!("[{timestamp}]")).dim())
+}
+
+/// Sets whether running tasks show a live clock of how long they have run, e.g. `- compiling 00:42`.
+pub fn set_show_clock(show: bool) {
+    SHOW_CLOCK.store(show, Ordering::Relaxed);
+}
+
+/// Sets whether running tasks show their counts, rates and how long they
+/// have run in a column at the right edge of the terminal:
+///
+/// ```text
+/// - compiling                                    (3/10) 00:42
+/// - downloading     2.1 MB / 8.0 MB (1.3 MB/s, ETA 00:05) 00:02
+/// ```
+///
+/// The column stays at the edge as the terminal is resized, and the
+/// message is cut short before it runs into the column.
+pub fn set_status_column(show: bool) {
+    STATUS_COLUMN.store(show, Ordering::Relaxed);
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Task {
+    pub id: u64,
+    /// the task this is a subtask of, if it is still running
+    pub parent: Option<u64>,
+    /// the context the task was started in by the macros, if any
+    pub context: Option<u64>,
+    /// how deeply the task is nested, where 0 is a task of its own
+    pub depth: usize,
+    pub message: String,
+    /// shown before the message, both while running and once ended
+    pub icon: Option<String>,
+    /// shown after the message as `key=value` pairs, both while running and once ended
+    pub fields: Vec<(String, String)>,
+    /// the name the task can be ended by without its handle, if it was given one
+    pub name: Option<String>,
+    pub started: Instant,
+    /// when the task last started, ended or updated anything, or made progress
+    pub active: Instant,
+    /// when the task times out, if it has a timeout
+    pub deadline: Option<Instant>,
+    pub spinner: SpinnerStyle,
+    /// the current step of the spinner animation, which
+    /// only advances in the renderer's copy of the task
+    pub frame: usize,
+    /// when the spinner should advance to its next frame
+    pub next_frame: Instant,
+    pub progress: Option<Progress>,
+    /// set if the task is nested too deeply to be drawn
+    pub hidden: bool,
+    /// the outcomes of hidden subtasks, for drawn tasks
+    pub rollup: Rollup,
+    /// the last lines of output shown beneath the task, like those of a child process
+    pub tail: VecDeque<String>,
+    /// how many lines the live tail keeps
+    pub tail_length: usize,
+    /// the worst outcome of the subtasks of a group so far,
+    /// or `None` if the task is not a group
+    pub group: Option<Outcome>,
+    /// set once the task's message has been printed in quiet mode
+    pub reported: bool,
+    /// set until the task is drawn, while it has not run for long enough to be
+    pub pending: bool,
+    /// how much the task counts towards the progress of its parent
+    pub weight: f64,
+    /// the combined weight of the subtasks with progress that have ended
+    pub stages_done: f64,
+    /// the phases that subtasks of the task are as they start, as `(name, weight)`
+    pub phases: Vec<(String, f64)>,
+    /// how many subtasks have started as phases of the task
+    pub phases_started: usize,
+    /// which phase of its parent the task is, if it is one
+    pub phase: Option<usize>,
+    /// how well the task is going while it runs, which tints its spinner and message
+    pub running_state: RunningState,
+}
+
+/// Counts of how the hidden subtasks of a task ended.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Rollup {
+    pub total: u32,
+    pub warned: u32,
+    pub failed: u32,
+}
+
+impl Rollup {
+    pub fn add(&mut self, outcome: Outcome) {
+        self.total += 1;
+
+        match outcome {
+            Outcome::Warn => self.warned += 1,
+            Outcome::Fail => self.failed += 1,
+            Outcome::Pass | Outcome::Skip => {}
+        }
+    }
+
+    /// Returns the worst outcome among the hidden subtasks,
+    /// if any of them did not pass.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.failed > 0 {
+            Some(Outcome::Fail)
+        } else if self.warned > 0 {
+            Some(Outcome::Warn)
+        } else {
+            None
+        }
+    }
+
+    /// Returns what is appended to the message of the task, e.g. ` (1 failed, 2 warned of 14 hidden)`.
+    pub fn suffix(&self) -> String {
+        let counts: Vec<String> = [(self.failed, "failed"), (self.warned, "warned")]
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{count} {label}"))
+            .collect();
+
+        if counts.is_empty() {
+            String::new()
+        } else {
+            format!(" ({} of {} hidden)", counts.join(", "), self.total)
+        }
+    }
+}
+
+impl Task {
+    /// Returns everything drawn after the spinner on the task's line,
+    /// except for what `status` puts in the status column.
+    pub fn body(&self, tasks: &[Task]) -> String {
+        let column = STATUS_COLUMN.load(Ordering::Relaxed);
+        let message = match self.tint() {
+            Some(color) => style(&self.message).color(color).to_string(),
+            None => self.message.clone(),
+        };
+
+        let mut body = self.icon_prefix() + &message + &self.fields_suffix();
+
+        if let Some(phase) = self.active_phase(tasks) {
+            body += &format!(" {}", style(phase).dim());
+        }
+
+        match &self.progress {
+            Some(progress) if column && progress.style != ProgressStyle::Bar => {}
+            Some(progress) => {
+                body += " ";
+                body += &progress.render();
+            }
+            None => {
+                if let Some(fraction) = self.completion(tasks) {
+                    body += " ";
+                    body += &bar(fraction);
+                }
+            }
+        }
+
+        if SHOW_CLOCK.load(Ordering::Relaxed) && !column {
+            body += " ";
+            body += &format::clock(self.started.elapsed());
+        }
+
+        body
+    }
+
+    /// Returns what is drawn in the status column at the right edge of
+    /// the task's line, or `None` if there is no status column.
+    pub fn status(&self) -> Option<String> {
+        if !STATUS_COLUMN.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let mut status = String::new();
+
+        if let Some(progress) = self.progress.as_ref().filter(|progress| progress.style != ProgressStyle::Bar) {
+            status += &progress.render();
+            status += " ";
+        }
+
+        Some(status + &format::clock(self.started.elapsed()))
+    }
+
+    /// Returns the color the spinner and message are tinted with while the task runs, if any.
+    pub fn tint(&self) -> Option<Color> {
+        match self.running_state {
+            RunningState::Normal => None,
+            RunningState::Warning => Some(theme().warn.color),
+            RunningState::Error => Some(theme().fail.color),
+        }
+    }
+
+    /// Returns how long the task has gone without activity, if that is long enough for it to be stalled.
+    pub fn stalled(&self) -> Option<Duration> {
+        let timeout = Duration::from_millis(STALL_TIMEOUT.load(Ordering::Relaxed));
+        let idle = self.active.elapsed();
+
+        (!timeout.is_zero() && idle >= timeout).then_some(idle)
+    }
+
+    /// Returns how far along the task is from 0 to 1, if that is known.
+    ///
+    /// A task without progress of its own is as far along as its subtasks
+    /// with progress, weighted by `TaskHandle::set_weight`, where the ones
+    /// that have ended count as complete. A task with phases is as far along
+    /// as its phases, including those that have not started yet.
+    pub fn completion(&self, tasks: &[Task]) -> Option<f64> {
+        if let Some(progress) = &self.progress {
+            return Some(progress.fraction());
+        }
+
+        if !self.phases.is_empty() {
+            let total: f64 = self.phases.iter().map(|(_, weight)| weight).sum();
+            let running: f64 = tasks
+                .iter()
+                .filter(|child| child.parent == Some(self.id) && child.phase.is_some())
+                .map(|child| child.weight * child.completion(tasks).unwrap_or(0.0))
+                .sum();
+
+            return (total > 0.0).then(|| ((self.stages_done + running) / total).clamp(0.0, 1.0));
+        }
+
+        let (mut done, mut total) = (self.stages_done, self.stages_done);
+
+        for child in tasks.iter().filter(|child| child.parent == Some(self.id)) {
+            if let Some(fraction) = child.completion(tasks) {
+                done += child.weight * fraction;
+                total += child.weight;
+            }
+        }
+
+        (total > 0.0).then(|| (done / total).clamp(0.0, 1.0))
+    }
+
+    /// Returns the phase that the subtasks of the task are being run for, as
+    /// `fetch (1/3)`, if it has phases and one of them is running.
+    pub fn active_phase(&self, tasks: &[Task]) -> Option<String> {
+        let child = tasks.iter().rev().find(|child| child.parent == Some(self.id) && child.phase.is_some())?;
+        let index = child.phase?;
+        let (name, _) = self.phases.get(index)?;
+
+        Some(format!("{name} ({}/{})", index + 1, self.phases.len()))
+    }
+
+    /// Takes the next phase for a subtask that is starting, returning its index and weight.
+    pub fn next_phase(&mut self) -> Option<(usize, f64)> {
+        let index = self.phases_started;
+        let (_, weight) = self.phases.get(index)?;
+
+        self.phases_started += 1;
+        Some((index, *weight))
+    }
+
+    /// Like `stalled`, except that a task waiting on its subtasks is not stalled itself.
+    pub fn stalled_among(&self, tasks: &[Task]) -> Option<Duration> {
+        self.stalled().filter(|_| !tasks.iter().any(|other| other.parent == Some(self.id)))
+    }
+
+    /// Describes the task as it is right now.
+    pub fn info(&self, tasks: &[Task]) -> TaskInfo {
+        let state = match self.stalled_among(tasks) {
+            Some(idle) => TaskState::Stalled(idle),
+            None => TaskState::Running,
+        };
+
+        TaskInfo {
+            id: self.id,
+            parent: self.parent,
+            depth: self.depth,
+            message: self.message.clone(),
+            state,
+            started: self.started,
+            progress: self.completion(tasks),
+            fields: self.fields.clone(),
+            name: self.name.clone(),
+            running_state: self.running_state,
+        }
+    }
+
+    /// Returns the icon of the task followed by a space, if it has one.
+    pub fn icon_prefix(&self) -> String {
+        self.icon.as_ref().map(|icon| format!("{icon} ")).unwrap_or_default()
+    }
+
+    /// Returns the fields of the task dimmed after a space, if it has any.
+    pub fn fields_suffix(&self) -> String {
+        if self.fields.is_empty() {
+            return String::new();
+        }
+
+        format!(" {}", style(format::fields(&self.fields)).dim())
+    }
+
+    /// Returns what is appended to the task's final message.
+    pub fn end_suffix(&self) -> String {
+        let mut suffix = self.fields_suffix() + &self.rollup.suffix();
+
+        if SHOW_ELAPSED.load(Ordering::Relaxed) {
+            suffix += &format!(" ({})", format::duration(self.started.elapsed()));
+        }
+
+        suffix
+    }
+}
+
+/// How the progress of a task is displayed.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressStyle {
+    Bar,
+    Counter,
+    Bytes,
+}
+
+/// Determinate progress of a task, as a position out of a length.
+#[derive(Clone, Debug)]
+pub(crate) struct Progress {
+    pub style: ProgressStyle,
+    position: u64,
+    pub length: u64,
+    rate: Rate,
+}
+
+/// A smoothed measurement of how fast the position advances.
+#[derive(Clone, Debug)]
+struct Rate {
+    /// when the last sample was taken
+    sampled_at: Instant,
+    /// the position when the last sample was taken
+    sampled_position: u64,
+    /// the moving average in units per second, once there is one
+    per_second: Option<f64>,
+}
+
+impl Progress {
+    pub fn new(style: ProgressStyle, length: u64) -> Self {
+        let rate = Rate { sampled_at: Instant::now(), sampled_position: 0, per_second: None };
+
+        Self { style, position: 0, length, rate }
+    }
+
+    /// Moves to a new position, sampling the rate of progress.
+    pub fn set_position(&mut self, position: u64) {
+        self.position = position;
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.rate.sampled_at);
+
+        // samples taken too close together are mostly noise from
+        // how the caller happens to chunk its work, so wait until
+        // enough time has passed to measure a meaningful rate.
+        if elapsed < SAMPLE_INTERVAL {
+            return;
+        }
+
+        let advanced = position.saturating_sub(self.rate.sampled_position) as f64;
+        let sample = advanced / elapsed.as_secs_f64();
+
+        self.rate.per_second = Some(match self.rate.per_second {
+            Some(average) => SMOOTHING * sample + (1.0 - SMOOTHING) * average,
+            None => sample,
+        });
+        self.rate.sampled_at = now;
+        self.rate.sampled_position = position;
+    }
+
+    /// Returns the completed fraction between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        match self.length {
+            0 => 0.0,
+            length => (self.position.min(length) as f64) / (length as f64),
+        }
+    }
+
+    fn render(&self) -> String {
+        let fraction = self.fraction();
+
+        match self.style {
+            ProgressStyle::Bar => bar(fraction),
+            ProgressStyle::Counter => format!("({}/{})", self.position, self.length),
+            ProgressStyle::Bytes => {
+                let mut text = format!(
+                    "{} / {}",
+                    format::bytes(self.position as f64),
+                    format::bytes(self.length as f64),
+                );
+
+                if let Some(rate) = self.rate.per_second {
+                    text += &format!(" ({}/s", format::bytes(rate));
+
+                    let remaining = self.length.saturating_sub(self.position) as f64;
+
+                    if rate > 0.0 && remaining > 0.0 {
+                        let eta = Duration::from_secs_f64((remaining / rate).min(u32::MAX as f64));
+                        text += &format!(", ETA {}", format::clock(eta));
+                    }
+
+                    text += ")";
+                }
+
+                text
+            }
+        }
+    }
+}
+
+/// Renders a progress bar filled to a fraction, e.g. `[=====     ]  50%`.
+fn bar(fraction: f64) -> String {
+    let theme = theme();
+    let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+
+    format!(
+        "[{}{}] {:>3}%",
+        theme.bar_filled.repeat(filled),
+        theme.bar_empty.repeat(BAR_WIDTH - filled),
+        (fraction * 100.0).floor() as u8,
+    )
+}
+
+/// Options for a new task, filled in by the `start!` family of macros.
+#[doc(hidden)]
+#[derive(Clone, Debug, Default)]
+pub struct TaskOptions {
+    pub spinner: Option<SpinnerStyle>,
+    pub progress: Option<ProgressStyle>,
+    pub length: u64,
+    pub group: bool,
+    pub icon: Option<String>,
+    pub timeout: Option<Duration>,
+    pub fields: Vec<(String, String)>,
+    pub name: Option<String>,
+}
+
+/// A running task, as returned by `tasks`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TaskInfo {
+    /// The same id as `TaskHandle::id` of the task.
+    pub id: u64,
+    /// The task this is a subtask of.
+    pub parent: Option<u64>,
+    /// How deeply the task is nested, where 0 is a task of its own.
+    pub depth: usize,
+    pub message: String,
+    pub state: TaskState,
+    pub started: Instant,
+    /// How far along the task is from 0 to 1, if it shows progress.
+    pub progress: Option<f64>,
+    /// The fields given to `start!`, as `(key, value)` pairs in order.
+    pub fields: Vec<(String, String)>,
+    /// The name given to `start!`, if any.
+    pub name: Option<String>,
+    /// What was last given to `TaskHandle::set_state`.
+    pub running_state: RunningState,
+}
+
+/// Whether a running task is making progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskState {
+    Running,
+    /// The task has gone this long without activity, which
+    /// is longer than the timeout set with `set_stall_timeout`.
+    Stalled(Duration),
+}
+
+/// How well a running task is going, set with `TaskHandle::set_state`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RunningState {
+    /// The spinner and message are drawn as usual.
+    #[default]
+    Normal,
+    /// The spinner and message are tinted with the warning color of the theme.
+    Warning,
+    /// The spinner and message are tinted with the failure color of the theme.
+    Error,
+}
+
+/// A handle to a started task, used to report its progress.
+///
+/// Dropping a handle does not end its task, and using a handle
+/// after its task has ended does nothing.
+#[derive(Clone)]
+pub struct TaskHandle {
+    pub(crate) id: u64,
+    /// the logger the task was started on
+    pub(crate) logger: Logger,
+}
+
+impl TaskHandle {
+    /// Returns the id of the task, which `tasks` reports it by.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Replaces the message of the task without completing it.
+    pub fn update(&self, message: impl Into<String>) {
+        self.logger.update_task(Some(self.id), message.into())
+    }
+
+    /// Prints supplementary lines beneath the task, like `detail!`.
+    ///
+    /// The lines stay with the task, after its subtasks so far, even
+    /// if other tasks have started since.
+    pub fn detail(&self, text: impl Into<String>) {
+        self.logger.detail_task(Some(self.id), text.into())
+    }
+
+    /// Sets how many lines of output pushed with `push_line` are shown
+    /// beneath the task, at least one. The default is 5.
+    ///
+    /// ```no_run
+    /// let build = lytelog::start!("building").tail(3);
+    ///
+    /// for line in ["compiling a", "compiling b", "compiling c", "linking"] {
+    ///     build.push_line(line);
+    /// }
+    ///
+    /// build.pass("built");
+    /// ```
+    pub fn tail(self, lines: usize) -> Self {
+        self.logger.with_task(self.id, |task| task.tail_length = lines.max(1));
+        self
+    }
+
+    /// Adds a line of output to the live tail beneath the task, pushing out the oldest line once the tail is full.
+    ///
+    /// The tail is drawn dimmed while the task runs, so a long step shows
+    /// what it is doing without flooding the terminal, and is erased
+    /// once the task ends. Lines are always recorded as `TaskEvent::Log`.
+    pub fn push_line(&self, text: impl Into<String>) {
+        self.logger.tail_line(self.id, text.into())
+    }
+
+    /// Starts a subtask of this task.
+    ///
+    /// Subtasks started from different handles run side by side, each
+    /// on its own row, so a thread can be given a handle to start and
+    /// end its own subtasks while its siblings keep running.
+    pub fn start(&self, message: impl Into<String>) -> TaskHandle {
+        self.logger.start_under(Some(self.id), message.into(), TaskOptions::default())
+    }
+
+    /// Makes the macros on the current thread act on a subtree of this task until the context is dropped.
+    ///
+    /// Tasks started with `start!` in the context are subtasks of this
+    /// task, and `update!`, `log!` and `pass!` only ever act on tasks the
+    /// thread started in it, so worker threads can each run their own
+    /// stack of tasks without closing the tasks of other threads:
+    /// `items.par_iter().for_each(|item| { let _context = build.enter(); ... })`.
+    pub fn enter(&self) -> TaskContext {
+        let id = self.logger.enter(self.id);
+        TaskContext { id, logger: self.logger.clone(), thread: PhantomData }
+    }
+
+    /// Passes the task.
+    pub fn pass(&self, message: impl Into<String>) {
+        self.end(Outcome::Pass, message)
+    }
+
+    /// Ends the task with a warning.
+    pub fn warn(&self, message: impl Into<String>) {
+        self.end(Outcome::Warn, message)
+    }
+
+    /// Fails the task.
+    pub fn fail(&self, message: impl Into<String>) {
+        self.end(Outcome::Fail, message)
+    }
+
+    /// Skips the task.
+    pub fn skip(&self, message: impl Into<String>) {
+        self.end(Outcome::Skip, message)
+    }
+
+    /// Cancels the task, ending it and its running subtasks with the cancelled
+    /// symbol, which counts as skipped.
+    pub fn cancel(&self, message: impl Into<String>) {
+        self.logger.cancel_task(Some(self.id), message.into())
+    }
+
+    /// Ends the task with any `Symbol`.
+    pub fn end(&self, symbol: impl Into<Symbol>, message: impl Into<String>) {
+        self.logger.end_task(Some(self.id), symbol.into(), message.into())
+    }
+
+    /// Fails the task with an error, printing its causes beneath it.
+    pub fn fail_with(&self, error: &(impl Error + ?Sized), message: impl Into<String>) {
+        self.logger.fail_task(Some(self.id), error, Some(message.into()))
+    }
+
+    /// Sets the completed fraction of the task, from 0.0 to 1.0.
+    pub fn set_progress(&self, fraction: f32) {
+        self.with_progress(|progress| {
+            let fraction = f64::from(fraction.clamp(0.0, 1.0));
+            progress.set_position((fraction * progress.length as f64).round() as u64);
+        });
+    }
+
+    /// Sets the total amount of work that `inc` counts towards.
+    ///
+    /// Progress bars have a length of 100 until this is called,
+    /// so each `inc(1)` is one percent.
+    pub fn set_length(&self, length: u64) {
+        self.with_progress(|progress| progress.length = length);
+    }
+
+    /// Sets how much the task counts towards the progress of its parent, 1 by default.
+    ///
+    /// A parent without progress of its own shows a bar for how far along
+    /// its subtasks with progress are, so a stage that takes twice as long
+    /// as the others can be given a weight of 2.
+    pub fn set_weight(&self, weight: f64) {
+        self.logger.with_task(self.id, |task| task.weight = weight.max(0.0));
+    }
+
+    /// Marks how well the task is going before it ends, tinting its spinner
+    /// and message while it runs.
+    ///
+    /// A healthcheck that keeps failing while it retries can be shown in yellow
+    /// with `RunningState::Warning`, so whoever is watching sees that something
+    /// is wrong before the task ends. `RunningState::Normal` takes the tint away.
+    pub fn set_state(&self, state: RunningState) {
+        self.logger.with_task(self.id, |task| {
+            task.running_state = state;
+            task.active = Instant::now();
+        });
+    }
+
+    /// Declares the phases of the task as `(name, weight)` pairs, like `phases!`.
+    ///
+    /// See `Logger::set_phases`.
+    pub fn set_phases<S: Into<String>>(&self, phases: impl IntoIterator<Item = (S, f64)>) {
+        self.logger.phase_task(Some(self.id), phases.into_iter().map(|(name, weight)| (name.into(), weight)).collect())
+    }
+
+    /// Advances the position of the task by `delta`.
+    pub fn inc(&self, delta: u64) {
+        self.with_progress(|progress| {
+            progress.set_position(progress.position.saturating_add(delta));
+        });
+    }
+
+    /// Records that `bytes` more bytes of a transfer have completed.
+    pub fn advance(&self, bytes: u64) {
+        self.inc(bytes)
+    }
+
+    /// Advances the position of the task by one.
+    pub fn tick(&self) {
+        self.inc(1)
+    }
+
+    /// Runs a closure on the progress of the task, if it is running and has any.
+    fn with_progress(&self, f: impl FnOnce(&mut Progress)) {
+        self.logger.with_task(self.id, |task| {
+            if let Some(progress) = &mut task.progress {
+                task.active = Instant::now();
+                f(progress)
+            }
+        });
+    }
+}
+
+// task ids are unique across every logger, so they alone tell handles apart
+
+impl PartialEq for TaskHandle {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for TaskHandle {}
+
+impl Hash for TaskHandle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+impl fmt::Debug for TaskHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskHandle").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+/// A context entered on the current thread with `TaskHandle::enter`,
+/// which is left when this is dropped.
+///
+/// Tasks the thread started in the context that are still running
+/// once it is left are no longer acted on by the macros, but can
+/// still be ended through their handles.
+pub struct TaskContext {
+    id: u64,
+    logger: Logger,
+    /// contexts belong to the thread that entered them
+    thread: PhantomData<*const ()>,
+}
+
+impl Drop for TaskContext {
+    fn drop(&mut self) {
+        self.logger.leave(self.id)
+    }
+}
+
+impl fmt::Debug for TaskContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskContext").field("id", &self.id).finish_non_exhaustive()
+    }
+}
+
+/// A group started by `group!`, which ends once it is dropped.
+#[doc(hidden)]
+pub struct Group {
+    handle: TaskHandle,
+}
+
+impl Group {
+    pub(crate) fn new(handle: TaskHandle) -> Self {
+        Self { handle }
+    }
+}
+
+impl Drop for Group {
+    fn drop(&mut self) {
+        self.handle.logger.end_group(self.handle.id)
+    }
+}
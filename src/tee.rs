@@ -0,0 +1,66 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::{event::TaskEvent, format, style::strip_ansi, time::SystemTime};
+
+static TEE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Appends a plain text transcript of every task to a file.
+///
+/// Each start, update, log line and result is written on its own line
+/// with a timestamp, without any escape codes or spinner frames, while the
+/// terminal output carries on as usual. Calling this again switches to the
+/// new file.
+pub fn tee_to_file(path: impl AsRef<Path>) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *TEE.lock().unwrap() = Some(file);
+
+    Ok(())
+}
+
+/// Writes an event to the tee file, if there is one.
+pub(crate) fn write(event: &TaskEvent) {
+    let mut tee = TEE.lock().unwrap();
+    let Some(file) = tee.as_mut() else { return };
+
+    let (depth, label, message) = match event {
+        TaskEvent::Start { depth, message, fields, .. } => (*depth, "start", with_fields(message, fields)),
+        TaskEvent::Update { depth, message, .. } => (*depth, "update", message.clone()),
+        TaskEvent::Log { depth, message, .. } => (*depth, "log", message.clone()),
+        TaskEvent::End { depth, symbol, message, elapsed, fields, .. } => {
+            let mut message = match symbol.custom_glyph() {
+                Some(glyph) => format!("{glyph} {}", with_fields(message, fields)),
+                None => with_fields(message, fields),
+            };
+
+            if let Some(elapsed) = elapsed {
+                message += &format!(" ({})", format::duration(*elapsed));
+            }
+
+            (*depth, symbol.outcome().name(), message)
+        }
+    };
+
+    let line = format!(
+        "{} {}{label:<6} {}\n",
+        format::timestamp(SystemTime::now()),
+        "  ".repeat(depth),
+        strip_ansi(&message),
+    );
+
+    // a transcript that can not be written should never stop
+    // the program, so write errors are ignored.
+    _ = file.write_all(line.as_bytes());
+}
+
+/// Returns a message followed by the fields of its task, if it has any.
+fn with_fields(message: &str, fields: &[(String, String)]) -> String {
+    match fields {
+        [] => message.to_string(),
+        fields => format!("{message} {}", format::fields(fields)),
+    }
+}
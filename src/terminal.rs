@@ -0,0 +1,233 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// A standard stream that may be a terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn fd(self) -> u8 {
+        match self {
+            Stream::Stdout => 1,
+            Stream::Stderr => 2,
+        }
+    }
+}
+
+/// The streams the cursor is hidden on, by file descriptor.
+static CURSOR_HIDDEN: AtomicU8 = AtomicU8::new(0);
+
+/// Returns the width and height of the terminal on a stream, if it is a terminal.
+///
+/// There is no terminal to measure in WebAssembly.
+pub(crate) fn size(stream: Stream) -> Option<(usize, usize)> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::io;
+        use terminal_size::{terminal_size_of, Height, Width};
+
+        let size = match stream {
+            Stream::Stdout => terminal_size_of(io::stdout()),
+            Stream::Stderr => terminal_size_of(io::stderr()),
+        };
+
+        size.map(|(Width(width), Height(height))| (usize::from(width), usize::from(height)))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        _ = stream;
+        None
+    }
+}
+
+/// Returns true if a stream is a terminal rather than a file or a pipe.
+///
+/// WebAssembly has no terminal, but nothing to redirect to either.
+pub(crate) fn is_terminal(stream: Stream) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::io::{self, IsTerminal};
+
+        match stream {
+            Stream::Stdout => io::stdout().is_terminal(),
+            Stream::Stderr => io::stderr().is_terminal(),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        _ = stream;
+        true
+    }
+}
+
+/// Returns false if the terminfo entry of `TERM` says the terminal can not
+/// move the cursor up or erase the rest of a line.
+///
+/// Terminals without an entry are assumed to be capable, since plenty of
+/// systems, like slim containers, come without a terminfo database at all.
+pub(crate) fn moves_cursor() -> bool {
+    #[cfg(unix)]
+    {
+        static MOVES: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+        *MOVES.get_or_init(|| unix::terminfo().and_then(|entry| unix::moves_cursor(&entry)).unwrap_or(true))
+    }
+
+    #[cfg(not(unix))]
+    true
+}
+
+/// Starts listening for the terminal being resized.
+///
+/// On Unix this installs a handler for `SIGWINCH`. Elsewhere there is
+/// no signal to wait for, so the terminal is measured every time.
+pub(crate) fn watch() {
+    #[cfg(unix)]
+    unix::watch();
+}
+
+/// Returns how many times the terminal has been resized, if that is known.
+///
+/// Without a count, the terminal may have been resized at any time.
+pub(crate) fn resizes() -> Option<usize> {
+    #[cfg(unix)]
+    return unix::resizes();
+
+    #[cfg(not(unix))]
+    None
+}
+
+/// Marks the cursor of a stream as hidden, making sure it is shown again when
+/// the process exits, and returns true if it was not hidden already.
+pub(crate) fn hide_cursor(stream: Stream) -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static AT_EXIT: std::sync::Once = std::sync::Once::new();
+
+        // SAFETY: `show_cursor_at_exit` does not unwind and
+        // only uses what outlives the rest of the process.
+        AT_EXIT.call_once(|| unsafe {
+            libc::atexit(show_cursor_at_exit);
+        });
+    }
+
+    let bit = 1 << stream.fd();
+
+    CURSOR_HIDDEN.fetch_or(bit, Ordering::Relaxed) & bit == 0
+}
+
+/// Marks the cursor of a stream as shown, returning true if it was hidden.
+pub(crate) fn show_cursor(stream: Stream) -> bool {
+    let bit = 1 << stream.fd();
+    CURSOR_HIDDEN.fetch_and(!bit, Ordering::Relaxed) & bit != 0
+}
+
+/// Shows the cursor if the process exits while it is hidden,
+/// including when the main thread panics.
+///
+/// The scroll region of dashboard mode is given back as well, saving the
+/// cursor around it since setting the scroll region moves the cursor.
+#[cfg(not(target_arch = "wasm32"))]
+extern "C" fn show_cursor_at_exit() {
+    const SHOW: &[u8] = b"\x1b7\x1b[r\x1b8\x1b[?25h";
+
+    let hidden = CURSOR_HIDDEN.swap(0, Ordering::Relaxed);
+
+    // a renderer may be holding the lock on its stream, and will
+    // never let go of it, so the escape is written past it.
+    for stream in [Stream::Stdout, Stream::Stderr] {
+        if hidden & (1 << stream.fd()) != 0 {
+            // SAFETY: the buffer is valid for its whole length
+            unsafe {
+                libc::write(stream.fd().into(), SHOW.as_ptr().cast(), SHOW.len() as _);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        env, fs,
+        path::PathBuf,
+        sync::{atomic::{AtomicUsize, Ordering}, OnceLock},
+    };
+
+    use signal_hook::{consts::SIGWINCH, low_level};
+
+    static RESIZES: AtomicUsize = AtomicUsize::new(0);
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+
+    pub fn watch() {
+        INSTALLED.get_or_init(|| {
+            // SAFETY: incrementing an atomic is safe in a signal handler
+            let registered = unsafe {
+                low_level::register(SIGWINCH, || {
+                    RESIZES.fetch_add(1, Ordering::Relaxed);
+                })
+            };
+
+            // if the handler can not be installed, the
+            // terminal is measured every time instead.
+            registered.is_ok()
+        });
+    }
+
+    pub fn resizes() -> Option<usize> {
+        match INSTALLED.get() {
+            Some(true) => Some(RESIZES.load(Ordering::Relaxed)),
+            // resizes can not be counted without the handler
+            _ => None,
+        }
+    }
+
+    /// The index of `clr_eol` among the string capabilities of a terminfo entry.
+    const CLR_EOL: usize = 6;
+    /// The index of `cursor_up` among the string capabilities of a terminfo entry.
+    const CURSOR_UP: usize = 19;
+
+    /// Reads the compiled terminfo entry of `TERM`, looking where ncurses does.
+    pub fn terminfo() -> Option<Vec<u8>> {
+        let term = crate::environment::var("TERM")?;
+        let first = term.chars().next()?;
+
+        let mut directories: Vec<PathBuf> = Vec::new();
+        directories.extend(env::var_os("TERMINFO").map(PathBuf::from));
+        directories.extend(env::var_os("HOME").map(|home| PathBuf::from(home).join(".terminfo")));
+        directories.extend(env::var("TERMINFO_DIRS").iter().flat_map(|dirs| dirs.split(':')).map(PathBuf::from));
+        directories.extend(["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"].map(PathBuf::from));
+
+        // entries are kept under their first letter, or its hex code on macOS
+        directories.iter().find_map(|directory| {
+            fs::read(directory.join(first.to_string()).join(&term))
+                .or_else(|_| fs::read(directory.join(format!("{:x}", u32::from(first))).join(&term)))
+                .ok()
+        })
+    }
+
+    /// Returns whether a compiled terminfo entry can move the cursor
+    /// up and erase the rest of a line, or `None` if it is malformed.
+    ///
+    /// The format is described in term(5).
+    pub fn moves_cursor(entry: &[u8]) -> Option<bool> {
+        let short = |offset: usize| Some(i16::from_le_bytes([*entry.get(offset)?, *entry.get(offset + 1)?]));
+        let count = |index: usize| short(index * 2).and_then(|count| usize::try_from(count).ok());
+
+        let number_size = match short(0)? {
+            0o432 => 2,
+            0o1036 => 4,
+            _ => return None,
+        };
+
+        let (names, booleans, numbers, strings) = (count(1)?, count(2)?, count(3)?, count(4)?);
+
+        // the numbers start on an even byte
+        let start = (12 + names + booleans).next_multiple_of(2) + numbers * number_size;
+        let present = |index: usize| Some(index < strings && short(start + index * 2)? >= 0);
+
+        Some(present(CURSOR_UP)? && present(CLR_EOL)?)
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::{charset::default_theme, environment, Color, Outcome, SpinnerStyle};
+
+/// A glyph and the color it is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    pub text: &'static str,
+    pub color: Color,
+}
+
+impl Glyph {
+    pub const fn new(text: &'static str, color: Color) -> Self {
+        Self { text, color }
+    }
+}
+
+/// Every glyph, color and spacing used to draw tasks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub spinner: SpinnerStyle,
+    pub spinner_color: Color,
+    pub pass: Glyph,
+    pub warn: Glyph,
+    pub fail: Glyph,
+    pub skip: Glyph,
+    /// drawn for tasks that were running when the process was interrupted
+    pub interrupted: Glyph,
+    /// drawn for tasks that were cancelled on purpose, and for their subtasks
+    pub cancelled: Glyph,
+    /// connector drawn before a child that has later siblings
+    pub tee: &'static str,
+    /// connector drawn on rows between a child and its next sibling
+    pub vertical: &'static str,
+    /// connector drawn before the last child of a task
+    pub last: &'static str,
+    /// number of columns each level of nesting is indented by
+    pub indent: usize,
+    /// number of columns before the connector at each level of nesting
+    pub margin: usize,
+    pub bar_filled: &'static str,
+    pub bar_empty: &'static str,
+}
+
+impl Theme {
+    /// Only ASCII characters, for terminals without Unicode support.
+    pub fn ascii() -> Self {
+        Self {
+            pass: Glyph::new("[ok]", Color::Green),
+            warn: Glyph::new("[warn]", Color::Yellow),
+            fail: Glyph::new("[fail]", Color::Red),
+            skip: Glyph::new("[skip]", Color::Gray),
+            interrupted: Glyph::new("[int]", Color::Magenta),
+            cancelled: Glyph::new("[cancel]", Color::Gray),
+            tee: "|-- ",
+            vertical: "|",
+            last: "`-- ",
+            indent: 6,
+            margin: 2,
+            bar_filled: "#",
+            bar_empty: "-",
+            ..Self::default()
+        }
+    }
+
+    /// Small symbols and plain indentation without tree connectors.
+    pub fn minimal() -> Self {
+        Self {
+            spinner: SpinnerStyle::dots(),
+            spinner_color: Color::Cyan,
+            pass: Glyph::new("✓", Color::Green),
+            warn: Glyph::new("!", Color::Yellow),
+            fail: Glyph::new("✗", Color::Red),
+            skip: Glyph::new("-", Color::Gray),
+            interrupted: Glyph::new("^C", Color::Magenta),
+            cancelled: Glyph::new("/", Color::Gray),
+            tee: "",
+            vertical: "",
+            last: "",
+            indent: 2,
+            margin: 2,
+            bar_filled: "━",
+            bar_empty: "─",
+        }
+    }
+
+    /// Bright colors and heavy glyphs that stand out on any background.
+    pub fn high_contrast() -> Self {
+        Self {
+            spinner_color: Color::BrightCyan,
+            pass: Glyph::new("✔", Color::BrightGreen),
+            warn: Glyph::new("▲", Color::BrightYellow),
+            fail: Glyph::new("✖", Color::BrightRed),
+            skip: Glyph::new("➔", Color::BrightWhite),
+            interrupted: Glyph::new("⊘", Color::BrightMagenta),
+            cancelled: Glyph::new("⊘", Color::BrightWhite),
+            ..Self::default()
+        }
+    }
+
+    /// Blue and orange instead of green and red, with every outcome
+    /// a different shape, for operators who can not tell red from green.
+    pub fn colorblind() -> Self {
+        Self {
+            spinner_color: Color::BrightBlue,
+            pass: Glyph::new("✔", Color::BrightBlue),
+            warn: Glyph::new("△", Color::Orange),
+            fail: Glyph::new("✖", Color::Orange),
+            skip: Glyph::new("○", Color::Gray),
+            interrupted: Glyph::new("⊘", Color::BrightWhite),
+            cancelled: Glyph::new("◌", Color::Gray),
+            ..Self::default()
+        }
+    }
+
+    /// Returns a built-in theme by name, which is one of `default`, `ascii`,
+    /// `minimal`, `high-contrast` and `colorblind`, ignoring case.
+    ///
+    /// This is how the `LYTELOG_THEME` environment variable is read,
+    /// and suits settings that come from a config file.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "default" => Some(Self::default()),
+            "ascii" => Some(Self::ascii()),
+            "minimal" => Some(Self::minimal()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "colorblind" => Some(Self::colorblind()),
+            _ => None,
+        }
+    }
+
+    /// Replaces the tree connectors with plain indentation, keeping everything else.
+    pub fn without_connectors(self) -> Self {
+        Self { tee: "", vertical: "", last: "", ..self }
+    }
+
+    /// Returns the glyph drawn for an outcome.
+    pub(crate) fn outcome(&self, outcome: Outcome) -> Glyph {
+        match outcome {
+            Outcome::Pass => self.pass,
+            Outcome::Warn => self.warn,
+            Outcome::Fail => self.fail,
+            Outcome::Skip => self.skip,
+        }
+    }
+}
+
+impl Default for Theme {
+    /// The classic lytelog look, with Unicode symbols and box-drawing connectors.
+    fn default() -> Self {
+        Self {
+            spinner: SpinnerStyle::default(),
+            spinner_color: Color::Yellow,
+            pass: Glyph::new("✔", Color::Green),
+            warn: Glyph::new("⚠", Color::Yellow),
+            fail: Glyph::new("𝕩", Color::Red),
+            skip: Glyph::new("➔", Color::Gray),
+            interrupted: Glyph::new("⊘", Color::Magenta),
+            cancelled: Glyph::new("⊘", Color::Gray),
+            tee: "┣━ ",
+            vertical: "┃",
+            last: "┗━ ",
+            indent: 5,
+            margin: 2,
+            bar_filled: "█",
+            bar_empty: "░",
+        }
+    }
+}
+
+static THEME: RwLock<Option<Arc<Theme>>> = RwLock::new(None);
+
+/// Sets the theme used to draw tasks.
+///
+/// Until this is called, the theme is the one named by the `LYTELOG_THEME`
+/// environment variable, as in `LYTELOG_THEME=colorblind`. Otherwise the
+/// default or ASCII theme is chosen by the charset.
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap() = Some(Arc::new(theme));
+}
+
+/// Returns the current theme.
+pub(crate) fn theme() -> Arc<Theme> {
+    static FROM_ENV: OnceLock<Option<Arc<Theme>>> = OnceLock::new();
+
+    if let Some(theme) = &*THEME.read().unwrap() {
+        return theme.clone();
+    }
+
+    let from_env = FROM_ENV.get_or_init(|| {
+        let name = environment::var("LYTELOG_THEME")?;
+        Theme::from_name(&name).map(Arc::new)
+    });
+
+    from_env.clone().unwrap_or_else(default_theme)
+}
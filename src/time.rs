@@ -0,0 +1,10 @@
+//! The clocks tasks are timed with.
+//!
+//! The standard clocks panic in the browser, so with the `wasm`
+//! feature they are replaced by ones that ask the browser instead.
+
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
+pub(crate) use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub(crate) use web_time::{Instant, SystemTime, UNIX_EPOCH};
@@ -0,0 +1,236 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{event::TaskEvent, output::push_string, style::strip_ansi, time::Instant, Outcome};
+
+static TRACE: Mutex<Option<Trace>> = Mutex::new(None);
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// When every task so far started and ended.
+struct Trace {
+    /// when the first task started, which the trace starts at
+    origin: Instant,
+    running: HashMap<u64, Span>,
+    ended: Vec<Span>,
+}
+
+#[derive(Clone)]
+struct Span {
+    id: u64,
+    parent: Option<u64>,
+    message: String,
+    started: Instant,
+    /// when and how the task ended, with its final message
+    end: Option<(Instant, Outcome, String)>,
+}
+
+/// Sets whether the start and end of every task is recorded for `export_trace`.
+///
+/// Nothing is recorded until this is called, since the trace keeps every
+/// task for as long as the program runs, which would grow without end in
+/// servers and watch modes. Turning it off keeps what was recorded so far.
+pub fn set_record_trace(enabled: bool) {
+    RECORDING.store(enabled, Ordering::Relaxed);
+}
+
+/// Writes every task recorded so far to a file in the Chrome trace event format.
+///
+/// Tasks are only recorded while [`set_record_trace`] is on, which it is not
+/// until it is called, so without it the file holds a trace with no events.
+///
+/// The file can be opened in `chrome://tracing`, Perfetto or Speedscope to see
+/// the run as a timeline, with subtasks nested under their parents, which makes
+/// slow steps easy to find. Tasks that are still running end at the time of the
+/// export. Tasks of different threads that overlap are put on lanes of their own.
+pub fn export_trace(path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, chrome_trace())
+}
+
+/// Adds an event to the trace.
+pub(crate) fn record(event: &TaskEvent) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut trace = TRACE.lock().unwrap();
+
+    match event {
+        TaskEvent::Start { id, parent, message, .. } => {
+            let trace = trace.get_or_insert_with(|| Trace {
+                origin: Instant::now(),
+                running: HashMap::new(),
+                ended: Vec::new(),
+            });
+
+            let span = Span { id: *id, parent: *parent, message: strip_ansi(message), started: Instant::now(), end: None };
+            trace.running.insert(*id, span);
+        }
+        TaskEvent::End { id: Some(id), symbol, message, .. } => {
+            let Some(trace) = trace.as_mut() else { return };
+            let Some(mut span) = trace.running.remove(id) else { return };
+
+            span.end = Some((Instant::now(), symbol.outcome(), strip_ansi(message)));
+            trace.ended.push(span);
+        }
+        _ => {}
+    }
+}
+
+/// Returns the spans of the trace as a Chrome trace event JSON document.
+fn chrome_trace() -> String {
+    let mut json = String::from("{\"traceEvents\":[");
+
+    if let Some(trace) = TRACE.lock().unwrap().as_ref() {
+        trace.write_events(&mut json, Instant::now());
+    }
+
+    json += "]}\n";
+    json
+}
+
+impl Trace {
+    /// Appends the spans as trace events, with those still running ending at `now`.
+    fn write_events(&self, json: &mut String, now: Instant) {
+        let mut spans: Vec<&Span> = self.ended.iter().chain(self.running.values()).collect();
+        let end = |span: &Span| span.end.as_ref().map_or(now, |(end, ..)| *end);
+
+        // parents start before their subtasks, and enclose the ones that start at the same time
+        spans.sort_by_key(|span| (span.started, now - end(span), span.id));
+
+        let micros = |duration: Duration| duration.as_micros() as u64;
+        let mut lanes: Vec<Vec<(u64, Instant)>> = Vec::new();
+        let mut lane_of: HashMap<u64, usize> = HashMap::new();
+
+        for (index, span) in spans.iter().enumerate() {
+            let (started, ended) = (span.started, end(span));
+
+            // a lane is a stack of spans nested in each other, so a span only goes
+            // on a lane if it fits inside the innermost span still open there.
+            for lane in &mut lanes {
+                while lane.last().is_some_and(|(_, end)| *end <= started) {
+                    lane.pop();
+                }
+            }
+
+            let fits = |lane: &Vec<(u64, Instant)>| lane.last().is_none_or(|(_, end)| *end >= ended);
+            let preferred = span.parent.and_then(|parent| lane_of.get(&parent).copied()).filter(|&lane| fits(&lanes[lane]));
+
+            let lane = match preferred.or_else(|| lanes.iter().position(|lane| lane.is_empty())) {
+                Some(lane) => lane,
+                None => {
+                    lanes.push(Vec::new());
+                    lanes.len() - 1
+                }
+            };
+
+            lanes[lane].push((span.id, ended));
+            lane_of.insert(span.id, lane);
+
+            if index > 0 {
+                json.push(',');
+            }
+
+            *json += "{\"name\":";
+            push_string(json, &span.message);
+            _ = write!(
+                json,
+                ",\"cat\":\"task\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{},\"dur\":{},\"args\":{{",
+                lane + 1,
+                micros(started.duration_since(self.origin)),
+                micros(ended.duration_since(started)),
+            );
+
+            match &span.end {
+                Some((_, outcome, message)) => {
+                    *json += "\"outcome\":";
+                    push_string(json, outcome.name());
+                    *json += ",\"message\":";
+                    push_string(json, message);
+                }
+                None => *json += "\"outcome\":\"running\"",
+            }
+
+            *json += "}}";
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(id: u64, parent: Option<u64>, origin: Instant, millis: (u64, u64)) -> Span {
+        let at = |millis| origin + Duration::from_millis(millis);
+        let end = (at(millis.1), Outcome::Pass, format!("task {id}"));
+
+        Span { id, parent, message: format!("task {id}"), started: at(millis.0), end: Some(end) }
+    }
+
+    /// Returns the lane of every event in the trace, in order.
+    fn lanes(trace: &Trace, now: Instant) -> Vec<(String, u64)> {
+        let mut json = String::new();
+        trace.write_events(&mut json, now);
+
+        json.split("{\"name\":\"")
+            .skip(1)
+            .map(|event| {
+                let name = event[..event.find('"').unwrap()].to_string();
+                let tid = event.split("\"tid\":").nth(1).unwrap();
+                (name, tid[..tid.find(',').unwrap()].parse().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events() {
+        let origin = Instant::now();
+        let mut running = HashMap::new();
+        running.insert(2, Span { message: "copy \"assets\"".to_string(), end: None, ..span(2, Some(1), origin, (5, 0)) });
+
+        let trace = Trace { origin, running, ended: vec![span(1, None, origin, (0, 20))] };
+        let mut json = String::new();
+        trace.write_events(&mut json, origin + Duration::from_millis(15));
+
+        assert_eq!(
+            json,
+            "{\"name\":\"task 1\",\"cat\":\"task\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":0,\"dur\":20000,\
+             \"args\":{\"outcome\":\"pass\",\"message\":\"task 1\"}},\
+             {\"name\":\"copy \\\"assets\\\"\",\"cat\":\"task\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":5000,\"dur\":10000,\
+             \"args\":{\"outcome\":\"running\"}}"
+        );
+    }
+
+    #[test]
+    fn overlapping_spans_get_lanes_of_their_own() {
+        let origin = Instant::now();
+
+        let ended = vec![
+            span(1, None, origin, (0, 100)),
+            span(2, Some(1), origin, (10, 50)),
+            // started on another thread while 2 runs, and outlasts it
+            span(3, None, origin, (20, 80)),
+            // a subtask of 3 goes on its lane even though it would fit inside 1
+            span(4, Some(3), origin, (30, 40)),
+            // once 3 is done, its lane is free again
+            span(5, None, origin, (85, 95)),
+        ];
+
+        let trace = Trace { origin, running: HashMap::new(), ended };
+        let lanes = lanes(&trace, origin + Duration::from_millis(100));
+
+        assert_eq!(
+            lanes,
+            [("task 1", 1), ("task 2", 1), ("task 3", 2), ("task 4", 2), ("task 5", 2)].map(|(name, tid)| (name.to_string(), tid))
+        );
+    }
+}
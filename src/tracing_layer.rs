@@ -0,0 +1,211 @@
+use std::fmt::{self, Write};
+
+use tracing_core::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use crate::{style::paint, Color, Logger, Outcome};
+
+/// A `tracing-subscriber` layer that draws spans as tasks.
+///
+/// A span starts a task the first time it is entered and ends it once
+/// the span closes. The task passes, unless a warning or error event was
+/// recorded inside of it, in which case it warns or fails with the message
+/// of that event. Other events are printed like `log!`.
+///
+/// Tasks are nested like the spans they were started for, so spans that
+/// are siblings in tracing are also siblings in the task tree, even while
+/// one of them is still open and another is entered:
+///
+/// ```
+/// use lytelog::{Logger, TaskLayer};
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let logger = Logger::with_writer(std::io::sink());
+/// let subscriber = tracing_subscriber::registry().with(TaskLayer::with_logger(logger.clone()));
+///
+/// tracing::subscriber::with_default(subscriber, || {
+///     let fetch = tracing::info_span!("fetch");
+///     let build = tracing::info_span!("build");
+///
+///     fetch.in_scope(|| {});
+///     build.in_scope(|| {});
+///
+///     let tasks = logger.tasks();
+///     assert_eq!(tasks.len(), 2);
+///     assert!(tasks.iter().all(|task| task.parent.is_none()));
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct TaskLayer {
+    logger: Logger,
+}
+
+impl TaskLayer {
+    /// Creates a layer that draws to the global logger.
+    pub fn new() -> Self {
+        Self::with_logger(Logger::global().clone())
+    }
+
+    /// Creates a layer that draws to a logger of its own.
+    pub fn with_logger(logger: Logger) -> Self {
+        Self { logger }
+    }
+}
+
+impl Default for TaskLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What is kept in the extensions of each span.
+struct SpanTask {
+    message: String,
+    /// the id of the task once the span has been entered
+    task: Option<u64>,
+    outcome: Outcome,
+    /// the message of the warning or error the task ends with
+    reason: Option<String>,
+}
+
+impl<S> Layer<S> for TaskLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+
+        let mut message = span.name().to_string();
+
+        for text in [fields.message, fields.rest] {
+            if !text.is_empty() {
+                message += " ";
+                message += &text;
+            }
+        }
+
+        span.extensions_mut().insert(SpanTask { message, task: None, outcome: Outcome::Pass, reason: None });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut extensions = span.extensions_mut();
+        let Some(task) = extensions.get_mut::<SpanTask>() else { return };
+
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+
+        if !fields.rest.is_empty() {
+            task.message += " ";
+            task.message += &fields.rest;
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+
+        // the task of the closest span above this one that has started
+        // its task, which is not always the task that started last
+        let parent = span.scope().skip(1).find_map(|parent| parent.extensions().get::<SpanTask>()?.task);
+
+        let mut extensions = span.extensions_mut();
+        let Some(task) = extensions.get_mut::<SpanTask>() else { return };
+
+        // async spans are entered every time their future is polled,
+        // but the task only starts the first time.
+        if task.task.is_none() {
+            task.task = Some(self.logger.start_under(parent, task.message.clone(), Default::default()).id);
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        let level = *event.metadata().level();
+        let text = match (fields.message.is_empty(), fields.rest.is_empty()) {
+            (_, true) => fields.message,
+            (true, false) => fields.rest,
+            (false, false) => format!("{} {}", fields.message, fields.rest),
+        };
+
+        let outcome = match level {
+            Level::ERROR => Some(Outcome::Fail),
+            Level::WARN => Some(Outcome::Warn),
+            _ => None,
+        };
+
+        if let (Some(outcome), Some(span)) = (outcome, ctx.event_span(event)) {
+            let mut extensions = span.extensions_mut();
+
+            if let Some(task) = extensions.get_mut::<SpanTask>().filter(|task| task.task.is_some()) {
+                // a failure is never downgraded by a later warning
+                if task.outcome != Outcome::Fail {
+                    task.outcome = outcome;
+                    task.reason = Some(text);
+                }
+
+                return;
+            }
+        }
+
+        let color = match level {
+            Level::ERROR => Color::Red,
+            Level::WARN => Color::Yellow,
+            Level::INFO => Color::Green,
+            Level::DEBUG => Color::Blue,
+            Level::TRACE => Color::Gray,
+        };
+
+        let level = level.as_str().to_ascii_lowercase();
+
+        self.logger.log_line(format!("{}: {text}", paint(color, &level)));
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(task) = span.extensions_mut().remove::<SpanTask>() else { return };
+
+        if let Some(id) = task.task {
+            self.logger.end_task(Some(id), task.outcome.into(), task.reason.unwrap_or(task.message));
+        }
+    }
+}
+
+/// Collects the fields of a span or event into text.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    /// every other field as `key=value` pairs
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.record_debug(field, &value)
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+            return;
+        }
+
+        if !self.rest.is_empty() {
+            self.rest += " ";
+        }
+
+        _ = write!(self.rest, "{}={value:?}", field.name());
+    }
+}